@@ -19,9 +19,12 @@
 # ------------------------------------------------------------------------------
 
 import os
+import sys
 from pydoc import locate
 
-from ffplayout.utils import _playout, stdin_args, validate_ffmpeg_libs
+from ffplayout.playlist import diff_playlists, dry_run, validate_range
+from ffplayout.utils import (_playout, get_date, stdin_args,
+                             validate_ffmpeg_libs)
 
 try:
     if os.name != 'posix':
@@ -59,6 +62,24 @@ def main():
 
 
 if __name__ == '__main__':
+    if stdin_args.dry_run is not None:
+        # only print the timeline, no ffmpeg process is started
+        sys.exit(dry_run(stdin_args.dry_run or get_date(True)))
+
+    if stdin_args.validate_range is not None:
+        # validate every day, no ffmpeg process is started
+        sys.exit(validate_range(*stdin_args.validate_range))
+
+    if stdin_args.diff is not None:
+        # compare two playlists, no ffmpeg process is started
+        sys.exit(diff_playlists(*stdin_args.diff, as_json=stdin_args.json))
+
+    if stdin_args.generate_playlist is not None:
+        # import here, folder mode needs watchdog
+        from ffplayout.generator import generate_playlist
+        sys.exit(generate_playlist(
+            stdin_args.generate_playlist or get_date(True)))
+
     # check if ffmpeg contains all codecs and filters
     validate_ffmpeg_libs()
     main()
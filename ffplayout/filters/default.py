@@ -23,7 +23,12 @@ import re
 from glob import glob
 from pydoc import locate
 
-from ffplayout.utils import _global, _pre, _text, is_advertisement, messenger
+from ffplayout.utils import (_global, _pre, _text, clip_speed, get_float,
+                             is_advertisement, messenger, parse_scale,
+                             vfr_info)
+
+# fade length in seconds, for crossfade without fade_in value
+CROSSFADE_LEN = 1.0
 
 # ------------------------------------------------------------------------------
 # building filters,
@@ -45,6 +50,99 @@ def text_filter():
     return filter_chain
 
 
+def escape_filter_value(value):
+    """
+    escape value for filter option and for filter graph level,
+    so colons, quotes, backslashes, commas, etc. stay in the text
+    """
+    value = re.sub(r"([\\':])", r'\\\1', str(value))
+
+    return re.sub(r"([\\'\[\],;])", r'\\\1', value)
+
+
+def clip_text_filter(node):
+    """
+    text overlay from clip values, like for lower thirds
+    """
+    filter_chain = []
+    text = node.get('text')
+
+    if text:
+        font = node.get('text_font') or _text.fontfile
+        color = node.get('text_color', 'white')
+        options = [
+            f'text={escape_filter_value(text)}',
+            'expansion=none',
+            f'x={escape_filter_value(node.get("text_x", 48))}',
+            f'y={escape_filter_value(node.get("text_y", "h-th-48"))}',
+            f'fontsize={node.get("text_size", 32)}',
+            f'fontcolor={escape_filter_value(color)}',
+            'box=1:boxcolor=black@0.5:boxborderw=8'
+        ]
+
+        if font and os.path.isfile(font):
+            options.append(f'fontfile={escape_filter_value(font)}')
+
+        filter_chain.append(f'drawtext={":".join(options)}')
+
+    return filter_chain
+
+
+def up_next_filter(node, node_next, length):
+    """
+    show title from the next clip in the last seconds of this clip,
+    length is in play time, no overlay over ads or without title
+    """
+    title = node_next.get('title') if node_next else None
+
+    if not _text.up_next or not title or is_advertisement(node) \
+            or is_advertisement(node_next):
+        return []
+
+    start = max(length - _text.up_next_lead, 0)
+    end = min(start + _text.up_next_duration, length)
+    font = _text.fontfile
+    options = [
+        f'text={escape_filter_value(_text.up_next_label + title)}',
+        'expansion=none',
+        'x=w-tw-48:y=48:fontsize=28:fontcolor=white',
+        'box=1:boxcolor=black@0.5:boxborderw=8',
+        f'enable={escape_filter_value(f"between(t,{start:.3f},{end:.3f})")}'
+    ]
+
+    if font and os.path.isfile(font):
+        options.append(f'fontfile={escape_filter_value(font)}')
+
+    return [f'drawtext={":".join(options)}']
+
+
+def subtitle_filter(node, seek):
+    """
+    burn in subtitles from sidecar file, the timestamps are moved
+    by the seek, so the subtitles fit also when the clip starts later
+    """
+    filter_chain = []
+    subtitle = node.get('subtitle')
+
+    if not subtitle:
+        return filter_chain
+
+    if not os.path.isfile(subtitle):
+        messenger.warning(
+            f'Subtitle "{subtitle}" not exists, play clip without it')
+        return filter_chain
+
+    if seek > 0:
+        filter_chain.append(f'setpts=PTS+{seek}/TB')
+
+    filter_chain.append(f'subtitles=filename={escape_filter_value(subtitle)}')
+
+    if seek > 0:
+        filter_chain.append('setpts=PTS-STARTPTS')
+
+    return filter_chain
+
+
 def deinterlace_filter(probe):
     """
     when material is interlaced,
@@ -78,13 +176,36 @@ def pad_filter(probe):
     return filter_chain
 
 
+def clip_scale_filter(node):
+    """
+    scale clip to its own size, and pad it in the middle of the output,
+    return None when the clip has no scale, then the default scaling is used
+    """
+    if not node.get('scale'):
+        return None
+
+    scale = parse_scale(node['scale'])
+
+    if scale is None:
+        messenger.warning(
+            f'Scale "{node["scale"]}" from "{node.get("source")}" is not '
+            f'valid, or bigger then {_pre.w}x{_pre.h}, use default scaling!')
+        return None
+
+    return [f'scale={scale[0]}:{scale[1]}', 'setsar=1',
+            f'pad={_pre.w}:{_pre.h}:(ow-iw)/2:(oh-ih)/2',
+            f'setdar=dar={_pre.aspect}']
+
+
 def fps_filter(probe):
     """
-    changing frame rate
+    changing frame rate, variable frame rate gets
+    normalized to the output frame rate, when vfr_fix is on
     """
     filter_chain = []
 
-    if probe.video[0]['fps'] != _pre.fps:
+    if probe.video[0]['fps'] != _pre.fps \
+            or _pre.vfr_fix and vfr_info(probe):
         filter_chain.append(f'fps={_pre.fps}')
 
     return filter_chain
@@ -108,21 +229,81 @@ def scale_filter(probe):
     return filter_chain
 
 
-def fade_filter(duration, seek, out, track=''):
+def clip_fades(node, node_next, length):
     """
-    fade in/out video, when is cutted at the begin or end
+    get fade in/out length from clip values, a crossfade fades
+    out the clip before and fades in the clip with the crossfade flag,
+    fades longer then the clip get clamped
+    """
+    fade_in = get_float(node.get('fade_in'), 0)
+    fade_out = get_float(node.get('fade_out'), 0)
+
+    if node.get('crossfade') and fade_in <= 0:
+        fade_in = CROSSFADE_LEN
+
+    if node_next and node_next.get('crossfade') and fade_out <= 0:
+        fade_out = get_float(node_next.get('fade_in'), 0) or CROSSFADE_LEN
+
+    if fade_in > length or fade_out > length:
+        messenger.warning(
+            f'Fade is longer then clip "{node.get("source")}", '
+            f'cut it to {length:.2f} seconds')
+        fade_in = min(fade_in, length)
+        fade_out = min(fade_out, length)
+
+    return fade_in, fade_out
+
+
+def fade_filter(duration, seek, out, track='', fade_in=0, fade_out=0):
+    """
+    fade in/out video, when is cutted at the begin or end,
+    or when the clip has fade values
     """
     filter_chain = []
 
-    if seek > 0.0:
+    if fade_in > 0:
+        filter_chain.append(f'{track}fade=in:st=0:d={fade_in}')
+    elif seek > 0.0:
         filter_chain.append(f'{track}fade=in:st=0:d=0.5')
 
-    if out != duration and out - seek - 1.0 > 0:
+    if fade_out > 0:
+        filter_chain.append(
+            f'{track}fade=out:st={out - seek - fade_out}:d={fade_out}')
+    elif out != duration and out - seek - 1.0 > 0:
         filter_chain.append(f'{track}fade=out:st={out - seek - 1.0}:d=1.0')
 
     return filter_chain
 
 
+def tempo_filter(node, track=''):
+    """
+    change playback speed from clip, setpts for video and atempo for audio,
+    atempo takes only factors from 0.5 to 2.0, so it gets chained,
+    it comes at the end, so the filters before work in clip time
+    """
+    speed = clip_speed(node)
+
+    if speed == 1.0:
+        return []
+
+    if track != 'a':
+        return [f'setpts=PTS/{speed}', f'fps={_pre.fps}']
+
+    filter_chain = []
+
+    while speed > 2.0:
+        filter_chain.append('atempo=2.0')
+        speed /= 2.0
+
+    while speed < 0.5:
+        filter_chain.append('atempo=0.5')
+        speed /= 0.5
+
+    filter_chain.append(f'atempo={round(speed, 6)}')
+
+    return filter_chain
+
+
 def overlay_filter(duration, ad, ad_last, ad_next):
     """
     overlay logo: when is an ad don't overlay,
@@ -152,41 +333,89 @@ def overlay_filter(duration, ad, ad_last, ad_next):
     return logo_filter
 
 
-def add_audio(probe, duration):
+def add_audio(probe, duration, mute=False):
     """
-    when clip has no audio we generate an audio line
+    when clip has no audio, or is muted, we generate an audio line
     """
     line = []
 
-    if not probe.audio:
-        messenger.warning(f'Clip "{probe.src}" has no audio!')
+    if mute or not probe.audio:
+        if not mute:
+            messenger.warning(f'Clip "{probe.src}" has no audio!')
         line = [(f'aevalsrc=0:channel_layout=2:duration={duration}:'
                  f'sample_rate={48000}')]
 
     return line
 
 
-def add_loudnorm(probe):
+def add_loudnorm(probe, node):
     """
-    add single pass loudnorm filter to audio line
+    add single pass loudnorm filter to audio line,
+    clips with "loudnorm": false are not normalized
     """
     loud_filter = []
 
-    if probe.audio and _pre.add_loudnorm:
+    if probe.audio and _pre.add_loudnorm and node.get('loudnorm', True):
         loud_filter = [
             f'loudnorm=I={_pre.loud_i}:TP={_pre.loud_tp}:LRA={_pre.loud_lra}']
 
     return loud_filter
 
 
-def extend_audio(probe, duration):
+def audio_track(node, probe, language=None):
+    """
+    get selected audio track from clip, for a language the track
+    from audio_tracks is taken, when track not exists take the first one
+    """
+    track = node.get('audio_track')
+    tracks = node.get('audio_tracks') or {}
+
+    if language in tracks:
+        track = tracks[language]
+
+    if track is None:
+        return 0
+
+    if type(track) is not int or not 0 <= track < len(probe.audio):
+        messenger.warning(
+            f'Audio track {track} not exists in "{probe.src}", '
+            'use first audio track!')
+        return 0
+
+    return track
+
+
+def audio_tracks(node, probe):
+    """
+    one audio track for every language output,
+    or only the selected track, when no languages are set
+    """
+    if not _pre.audio_languages:
+        return [audio_track(node, probe)]
+
+    return [audio_track(node, probe, lang) for lang in _pre.audio_languages]
+
+
+def language_metadata():
+    """
+    language tag for every audio stream
+    """
+    metadata = []
+
+    for num, lang in enumerate(_pre.audio_languages):
+        metadata += [f'-metadata:s:a:{num}', f'language={lang}']
+
+    return metadata
+
+
+def extend_audio(probe, duration, track=0):
     """
     check audio duration, is it shorter then clip duration - pad it
     """
     pad_filter = []
 
-    if probe.audio and 'duration' in probe.audio[0] and \
-            duration > float(probe.audio[0]['duration']) + 0.1:
+    if probe.audio and 'duration' in probe.audio[track] and \
+            duration > float(probe.audio[track]['duration']) + 0.1:
         pad_filter.append(f'apad=whole_dur={duration}')
 
     return pad_filter
@@ -269,57 +498,109 @@ def build_filtergraph(node, node_last, node_next):
     seek = node['seek']
     out = node['out']
     probe = node['probe']
+    mute = node.get('mute', False) or node.get('mode') == 'video'
+    # audio only clips get the video from the background input
+    background = node.get('mode') == 'audio' \
+        and 'background_probe' in node
+    v_probe = node.get('background_probe') if background else probe
+    v_input = '1:v' if background else '0:v'
 
     video_chain = []
-    audio_chain = []
+    audio_chains = []
 
     if out > duration:
         seek = 0
 
-    if probe and probe.video[0]:
+    fade_in, fade_out = clip_fades(node, node_next, out - seek)
+
+    if probe and (background or probe.video[0]):
         custom_v_filter = custom_filter('v', node)
         video_chain += text_filter()
-        video_chain += deinterlace_filter(probe)
-        video_chain += pad_filter(probe)
-        video_chain += fps_filter(probe)
-        video_chain += scale_filter(probe)
-        video_chain += extend_video(probe, duration, out - seek)
-        if custom_v_filter:
-            video_chain += custom_v_filter
-        video_chain += fade_filter(duration, seek, out)
 
-        audio_chain += add_audio(probe, out - seek)
+        if background:
+            # the looped background is endless
+            video_chain.append(f'trim=duration={out - seek}')
 
-        if not audio_chain:
-            custom_a_filter = custom_filter('a', node)
+        if v_probe:
+            video_chain += deinterlace_filter(v_probe)
+            clip_scale = clip_scale_filter(node)
 
-            audio_chain.append('[0:a]anull')
-            audio_chain += add_loudnorm(probe)
-            audio_chain += extend_audio(probe, out - seek)
-            if custom_a_filter:
-                audio_chain += custom_a_filter
-            audio_chain += fade_filter(duration, seek, out, 'a')
+            if clip_scale is None:
+                video_chain += pad_filter(v_probe)
+                video_chain += fps_filter(v_probe)
+                video_chain += scale_filter(v_probe)
+            else:
+                video_chain += fps_filter(v_probe)
+                video_chain += clip_scale
+
+        if not background:
+            video_chain += extend_video(probe, duration, out - seek)
+        if custom_v_filter:
+            video_chain += custom_v_filter
+        video_chain += subtitle_filter(node, seek)
+        video_chain += clip_text_filter(node)
+        video_chain += fade_filter(duration, seek, out, '', fade_in, fade_out)
+        video_chain += tempo_filter(node)
+        video_chain += up_next_filter(node, node_next,
+                                      (out - seek) / clip_speed(node))
+
+        silence = add_audio(probe, out - seek, mute)
+        custom_a_filter = [] if silence else custom_filter('a', node)
+
+        # every language output gets its own audio chain
+        for track in audio_tracks(node, probe):
+            audio_chain = list(silence)
+
+            if not audio_chain:
+                audio_chain.append(f'[0:a:{track}]anull')
+                if background:
+                    audio_chain.append(f'atrim=duration={out - seek}')
+                audio_chain += add_loudnorm(probe, node)
+                audio_chain += extend_audio(probe, out - seek, track)
+                if custom_a_filter:
+                    audio_chain += custom_a_filter
+                audio_chain += fade_filter(duration, seek, out, 'a', fade_in,
+                                           fade_out)
+
+            audio_chains.append(audio_chain + tempo_filter(node, 'a'))
 
     if video_chain:
         video_filter = f'{",".join(video_chain)}[v]'
     else:
         video_filter = 'null[v]'
 
-    logo_filter = overlay_filter(out - seek, ad, ad_last, ad_next)
-    v_speed = realtime_filter(out - seek)
+    # logo and realtime come after the speed change, in play time
+    length = (out - seek) / clip_speed(node)
+    logo_filter = overlay_filter(length, ad, ad_last, ad_next)
+    v_speed = realtime_filter(length)
     v_split = split_filter('v')
     video_map = ['-map', '[vout1]']
     video_filter = [
         '-filter_complex',
-        f'[0:v]{video_filter};{logo_filter}{v_speed}{v_split}']
+        f'[{v_input}]{video_filter};{logo_filter}{v_speed}{v_split}']
+
+    if not probe or not (background or probe.video[0]):
+        # the generated audio is the same for all languages
+        return video_filter + video_map + ['-map', '1:a'] * max(
+            len(_pre.audio_languages), 1) + language_metadata()
+
+    a_speed = realtime_filter(length, 'a')
+
+    if _pre.audio_languages:
+        audio_map = []
+        links = []
 
-    a_speed = realtime_filter(out - seek, 'a')
-    a_split = split_filter('a')
-    audio_map = ['-map', '[aout1]']
-    audio_filter = [
-        '-filter_complex', f'{",".join(audio_chain)}{a_speed}{a_split}']
+        for num, audio_chain in enumerate(audio_chains, 1):
+            links.append(f'{",".join(audio_chain)}{a_speed}[alang{num}]')
+            audio_map += ['-map', f'[alang{num}]']
 
-    if probe and probe.video[0]:
-        return video_filter + audio_filter + video_map + audio_map
+        audio_filter = ['-filter_complex', ';'.join(links)]
     else:
-        return video_filter + video_map + ['-map', '1:a']
+        a_split = split_filter('a')
+        audio_map = ['-map', '[aout1]']
+        audio_filter = [
+            '-filter_complex',
+            f'{",".join(audio_chains[0])}{a_speed}{a_split}']
+
+    return video_filter + audio_filter + video_map + audio_map + \
+        language_metadata()
@@ -27,7 +27,8 @@ from watchdog.events import PatternMatchingEventHandler
 from watchdog.observers import Observer
 
 from .filters.default import build_filtergraph
-from .utils import MediaProbe, _ff, _storage, messenger, stdin_args
+from .utils import (MediaProbe, _ff, _global, _storage, load_config,
+                    messenger, stdin_args)
 
 # ------------------------------------------------------------------------------
 # folder watcher
@@ -154,6 +155,12 @@ class GetSourceFromFolder:
     def next(self):
         while True:
             while self.index < len(self._media.store):
+                if _global.reload:
+                    # SIGHUP only sets the flag, config is loaded here
+                    _global.reload = False
+                    messenger.info('Reload config file')
+                    load_config()
+
                 if self.node_next:
                     self.node = deepcopy(self.node_next)
                     self.probe = deepcopy(self.next_probe)
@@ -0,0 +1,129 @@
+# -*- coding: utf-8 -*-
+
+# This file is part of ffplayout.
+#
+# ffplayout is free software: you can redistribute it and/or modify
+# it under the terms of the GNU General Public License as published by
+# the Free Software Foundation, either version 3 of the License, or
+# (at your option) any later version.
+#
+# ffplayout is distributed in the hope that it will be useful,
+# but WITHOUT ANY WARRANTY; without even the implied warranty of
+# MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+# GNU General Public License for more details.
+#
+# You should have received a copy of the GNU General Public License
+# along with ffplayout. If not, see <http://www.gnu.org/licenses/>.
+
+# ------------------------------------------------------------------------------
+
+import json
+import os
+
+from .folder import MediaStore
+from .utils import (MediaProbe, _playlist, _playout, get_float, messenger,
+                    playlist_file, probe_cache)
+
+# the day is full, when less seconds are left
+MIN_CUT = 0.001
+
+# ------------------------------------------------------------------------------
+# generate a playlist for one day from folder content
+# ------------------------------------------------------------------------------
+
+
+def probe_clips(files):
+    """
+    get duration from every file, files without duration are skipped
+    """
+    clips = []
+
+    for file in files:
+        probe = MediaProbe()
+        probe.load(file)
+        duration = get_float(
+            probe.format.get('duration') if probe.format else None, 0)
+
+        if duration > 0:
+            clips.append({
+                'in': 0,
+                'out': duration,
+                'duration': duration,
+                'source': file
+            })
+        else:
+            messenger.warning(f'Clip "{file}" has no duration, skip it')
+
+    probe_cache.save()
+
+    return clips
+
+
+def generate_playlist(list_date):
+    """
+    fill one day with the clips from storage folder, sorted or shuffled
+    like in folder mode, the last clip is cut to the playlist length,
+    return exit code
+    """
+    # playlist can only be written to a local path
+    paths = [path for path in _playlist.paths if '://' not in path]
+
+    if not paths:
+        messenger.error('No local playlist path to write the playlist')
+        return 1
+
+    json_file = playlist_file(paths[0], list_date)
+
+    if os.path.isfile(json_file):
+        messenger.error(f'Playlist "{json_file}" exists already')
+        return 1
+
+    media = MediaStore()
+    media.sort_or_radomize()
+    clips = probe_clips(media.store)
+
+    if not clips:
+        messenger.error(f'No clips found in: "{media.folder}"')
+        return 1
+
+    target = _playlist.length or 86400.0
+    program = []
+    total = 0
+    full = False
+
+    # clips are repeated, when the folder is not enough for the day
+    while not full:
+        for clip in clips:
+            rest = target - total
+
+            if rest < MIN_CUT:
+                full = True
+                break
+
+            node = dict(clip)
+
+            if node['out'] >= rest:
+                # last clip, rounding can not give a zero length
+                node['out'] = round(rest, 3)
+                full = True
+
+            program.append(node)
+            total += node['out']
+
+            if full:
+                break
+
+    playlist = {
+        'channel': _playout.name,
+        'date': list_date,
+        'program': program
+    }
+
+    os.makedirs(os.path.dirname(json_file), exist_ok=True)
+
+    with open(json_file, 'w', encoding='utf-8') as f:
+        json.dump(playlist, f, indent=4)
+
+    messenger.info(f'Write playlist with {len(program)} clips: "{json_file}"')
+
+    return 0
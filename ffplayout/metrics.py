@@ -0,0 +1,138 @@
+# -*- coding: utf-8 -*-
+
+# This file is part of ffplayout.
+#
+# ffplayout is free software: you can redistribute it and/or modify
+# it under the terms of the GNU General Public License as published by
+# the Free Software Foundation, either version 3 of the License, or
+# (at your option) any later version.
+#
+# ffplayout is distributed in the hope that it will be useful,
+# but WITHOUT ANY WARRANTY; without even the implied warranty of
+# MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+# GNU General Public License for more details.
+#
+# You should have received a copy of the GNU General Public License
+# along with ffplayout. If not, see <http://www.gnu.org/licenses/>.
+
+# ------------------------------------------------------------------------------
+
+from http.server import BaseHTTPRequestHandler, HTTPServer
+from socketserver import ThreadingMixIn
+from threading import Thread
+
+from .playlist import stats
+from .utils import _metrics, messenger
+
+# ------------------------------------------------------------------------------
+# metrics in prometheus text format, for monitoring the playout
+# ------------------------------------------------------------------------------
+
+
+def metric(lines, name, kind, text, value):
+    """
+    add metric with help and type line, value can be
+    a dict with labels as keys
+    """
+    lines.append(f'# HELP ffplayout_{name} {text}')
+    lines.append(f'# TYPE ffplayout_{name} {kind}')
+
+    if not isinstance(value, dict):
+        value = {'': value}
+
+    for labels, val in value.items():
+        lines.append(f'ffplayout_{name}{labels} {val}')
+
+
+def collect(get_source):
+    """
+    read values from playlist source and counters
+    """
+    lines = []
+    values = stats.get()
+    node = get_source.current
+    status = get_source.status()
+
+    if status is not None:
+        metric(lines, 'clip_index', 'gauge',
+               'Index from current clip, -1 for placeholders',
+               -1 if status['index'] is None else status['index'])
+        metric(lines, 'clip_elapsed_seconds', 'gauge',
+               'Seconds played from current clip', status['elapsed'])
+        metric(lines, 'clip_remaining_seconds', 'gauge',
+               'Seconds left from current clip', status['remaining'])
+
+    metric(lines, 'fallback', 'gauge',
+           'Placeholder plays, because playlist is missing or to short',
+           int(node is not None and not node['number']))
+    metric(lines, 'playlist_clips', 'gauge',
+           'Clips in current playlist', len(get_source.clip_nodes))
+    metric(lines, 'playlist_modified_timestamp_seconds', 'gauge',
+           'Modification time from current playlist',
+           get_source.playlist.last_mod_time)
+    metric(lines, 'validation_warnings', 'gauge',
+           'Warnings from last playlist validation',
+           values['validation_warnings'])
+    metric(lines, 'validation_errors', 'gauge',
+           'Errors from last playlist validation',
+           values['validation_errors'])
+    metric(lines, 'remote_requests_total', 'counter',
+           'Requests for remote playlists', {
+               '{result="success"}': values['remote_success'],
+               '{result="failure"}': values['remote_failure']
+           })
+
+    return '\n'.join(lines) + '\n'
+
+
+class MetricsHandler(BaseHTTPRequestHandler):
+    """
+    answer GET requests with current metrics
+    """
+
+    def log_message(self, format, *args):
+        pass
+
+    def do_GET(self):
+        if self.path.split('?')[0] not in ['/', '/metrics']:
+            self.send_error(404)
+            return
+
+        body = collect(self.server.get_source).encode('utf-8')
+
+        self.send_response(200)
+        self.send_header('Content-Type', 'text/plain; version=0.0.4')
+        self.send_header('Content-Length', str(len(body)))
+        self.end_headers()
+        self.wfile.write(body)
+
+
+class MetricsServer(ThreadingMixIn, HTTPServer):
+    daemon_threads = True
+
+
+def metrics_server(get_source):
+    """
+    start metrics server in background thread, when it is enabled
+    """
+    if not _metrics.enable:
+        return None
+
+    host, port = _metrics.address.rsplit(':', 1)
+
+    try:
+        server = MetricsServer((host, int(port)), MetricsHandler)
+    except (OSError, ValueError) as err:
+        messenger.error(
+            f'Metrics server could not start on {_metrics.address}\n{err}')
+        return None
+
+    server.get_source = get_source
+
+    thread = Thread(name='metrics_server', target=server.serve_forever)
+    thread.daemon = True
+    thread.start()
+
+    messenger.info(f'Metrics server listening on: {_metrics.address}')
+
+    return server
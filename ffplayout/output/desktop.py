@@ -3,8 +3,10 @@ from subprocess import PIPE, Popen
 from threading import Thread
 
 from ffplayout.folder import GetSourceFromFolder, MediaStore, MediaWatcher
+from ffplayout.metrics import metrics_server
 from ffplayout.playlist import GetSourceFromPlaylist
-from ffplayout.utils import (_ff, _log, _playlist, _pre, _text,
+from ffplayout.rpc import rpc_server
+from ffplayout.utils import (_ff, _log, _playlist, _pre, _text, clip_meta,
                              ffmpeg_stderr_reader, messenger, pre_audio_codec,
                              stdin_args, terminate_processes)
 
@@ -53,6 +55,8 @@ def output():
         if _playlist.mode and not stdin_args.folder:
             watcher = None
             get_source = GetSourceFromPlaylist()
+            rpc_server(get_source)
+            metrics_server(get_source)
         else:
             messenger.info('Start folder mode')
             media = MediaStore()
@@ -66,7 +70,7 @@ def output():
 
                 messenger.info(
                     f'Play for {node["out"] - node["seek"]:.2f} '
-                    f'seconds: {node.get("source")}')
+                    f'seconds: {node.get("source")}', **clip_meta(node))
 
                 dec_cmd = [
                     'ffmpeg', '-v', _log.ff_level.lower(),
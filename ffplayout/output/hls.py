@@ -5,10 +5,12 @@ from subprocess import PIPE, Popen
 from threading import Thread
 
 from ffplayout.folder import GetSourceFromFolder, MediaStore, MediaWatcher
+from ffplayout.metrics import metrics_server
 from ffplayout.playlist import GetSourceFromPlaylist
-from ffplayout.utils import (_ff, _log, _playlist, _playout,
-                             ffmpeg_stderr_reader, get_date, messenger,
-                             stdin_args, terminate_processes)
+from ffplayout.rpc import rpc_server
+from ffplayout.utils import (_ff, _log, _playlist, _playout, clip_meta,
+                             daypart, ffmpeg_stderr_reader, get_date,
+                             messenger, stdin_args, terminate_processes)
 
 
 def clean_ts():
@@ -47,6 +49,8 @@ def output():
         if _playlist.mode and not stdin_args.folder:
             watcher = None
             get_source = GetSourceFromPlaylist()
+            rpc_server(get_source)
+            metrics_server(get_source)
         else:
             messenger.info('Start folder mode')
             media = MediaStore()
@@ -58,7 +62,8 @@ def output():
                 if watcher is not None:
                     watcher.current_clip = node.get('source')
 
-                messenger.info(f'Play: {node.get("source")}')
+                messenger.info(f'Play: {node.get("source")}',
+                               **clip_meta(node))
 
                 cmd = [
                     'ffmpeg', '-v', _log.ff_level.lower(), '-hide_banner',
@@ -67,7 +72,8 @@ def output():
                         '-metadata', 'service_name=' + _playout.name,
                         '-metadata', 'service_provider=' + _playout.provider,
                         '-metadata', 'year={}'.format(year)
-                    ] + _playout.ffmpeg_param + _playout.hls_output
+                    ] + daypart(node.get('begin')).ffmpeg_param + \
+                    _playout.hls_output
 
                 messenger.debug(f'Encoder CMD: "{" ".join(cmd)}"')
 
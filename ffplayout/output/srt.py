@@ -0,0 +1,212 @@
+import os
+from subprocess import PIPE, Popen
+from threading import Thread
+from urllib.parse import quote
+
+from ffplayout.folder import GetSourceFromFolder, MediaStore, MediaWatcher
+from ffplayout.metrics import metrics_server
+from ffplayout.playlist import GetSourceFromPlaylist
+from ffplayout.rpc import rpc_server
+from ffplayout.utils import (_ff, _log, _playlist, _playout, _pre, _text,
+                             clip_meta, daypart, ffmpeg_stderr_reader,
+                             get_date, is_terminated, messenger,
+                             pre_audio_codec, stdin_args, terminate_processes)
+
+_WINDOWS = os.name == 'nt'
+COPY_BUFSIZE = 1024 * 1024 if _WINDOWS else 65424
+# seconds to wait, before the encoder connects again
+RETRY_DELAY = 5
+
+
+def srt_url(hide_passphrase=False):
+    """
+    srt address with mode, latency and passphrase as url options,
+    latency is in milliseconds in config, ffmpeg needs microseconds
+    """
+    options = [f'mode={_playout.srt_mode}']
+
+    if _playout.srt_latency:
+        options.append(f'latency={int(float(_playout.srt_latency) * 1000)}')
+
+    if _playout.srt_passphrase:
+        passphrase = '***' if hide_passphrase else quote(
+            str(_playout.srt_passphrase), safe='')
+        options.append(f'passphrase={passphrase}')
+
+    separator = '&' if '?' in _playout.srt_output else '?'
+
+    return _playout.srt_output + separator + '&'.join(options)
+
+
+def encoder_cmd(options, part):
+    """
+    encoder command with the ffmpeg params from the daypart
+    """
+    year = get_date(False).split('-')[0]
+    enc_cmd = [
+        'ffmpeg', '-v', _log.ff_level.lower(), '-hide_banner',
+        '-nostats'
+        ] + (['-re'] if _playout.realtime else []) + [
+            '-thread_queue_size', '160', '-i', 'pipe:0'
+        ] + options + [
+            '-metadata', 'service_name=' + _playout.name,
+            '-metadata', 'service_provider=' + _playout.provider,
+            '-metadata', f'year={year}'
+        ] + part.ffmpeg_param + ['-f', 'mpegts']
+
+    messenger.debug(f'Encoder CMD: "{" ".join(enc_cmd + [srt_url(True)])}"')
+
+    return enc_cmd + [srt_url()]
+
+
+def start_encoder(enc_cmd):
+    """
+    start encoder with stderr reader, which logs connection errors
+    """
+    encoder = Popen(enc_cmd, stdin=PIPE, stderr=PIPE)
+
+    enc_err_thread = Thread(target=ffmpeg_stderr_reader,
+                            args=(encoder.stderr, False))
+    enc_err_thread.daemon = True
+    enc_err_thread.start()
+
+    return encoder
+
+
+def write_or_reconnect(buf, enc_cmd):
+    """
+    write to encoder, when the srt connection fails the encoder stops,
+    then it is started again after RETRY_DELAY, the buffer is lost,
+    return False when playout is going down
+    """
+    try:
+        _ff.encoder.stdin.write(buf)
+        return True
+    except BrokenPipeError:
+        pass
+
+    if is_terminated.is_set():
+        return False
+
+    messenger.error(f'SRT connection to "{srt_url(True)}" failed, try '
+                    f'again in {RETRY_DELAY} seconds')
+    _ff.encoder.wait()
+
+    if is_terminated.wait(RETRY_DELAY):
+        return False
+
+    _ff.encoder = start_encoder(enc_cmd)
+
+    return True
+
+
+def output():
+    """
+    this output is for streaming over srt, in caller or listener mode,
+    the encoder connects again, when the connection fails
+    """
+    overlay = []
+    streams = []
+
+    if _playout.srt_passphrase \
+            and not 10 <= len(str(_playout.srt_passphrase)) <= 79:
+        messenger.error('SRT passphrase needs 10 to 79 characters')
+        return
+
+    ff_pre_settings = [
+        '-pix_fmt', 'yuv420p', '-r', str(_pre.fps),
+        '-c:v', 'mpeg2video', '-intra',
+        '-b:v', f'{_pre.v_bitrate}k',
+        '-minrate', f'{_pre.v_bitrate}k',
+        '-maxrate', f'{_pre.v_bitrate}k',
+        '-bufsize', f'{_pre.v_bufsize}k'
+        ] + pre_audio_codec() + ['-f', 'mpegts', '-']
+
+    if _text.add_text and not _text.over_pre:
+        messenger.info(
+            f'Using drawtext node, listening on address: {_text.address}')
+        overlay = [
+            '-vf',
+            "null,zmq=b=tcp\\\\://'{}',drawtext=text='':fontfile='{}'".format(
+                _text.address.replace(':', '\\:'), _text.fontfile)
+        ]
+
+    if _pre.audio_languages:
+        # mpegts can carry all language tracks
+        streams = ['-map', '0:v', '-map', '0:a']
+
+    part = daypart()
+    enc_cmd = encoder_cmd(streams + overlay, part)
+
+    try:
+        _ff.encoder = start_encoder(enc_cmd)
+
+        if _playlist.mode and not stdin_args.folder:
+            watcher = None
+            get_source = GetSourceFromPlaylist()
+            rpc_server(get_source)
+            metrics_server(get_source)
+        else:
+            messenger.info('Start folder mode')
+            media = MediaStore()
+            watcher = MediaWatcher(media)
+            get_source = GetSourceFromFolder(media)
+
+        try:
+            for node in get_source.next():
+                if watcher is not None:
+                    watcher.current_clip = node.get('source')
+
+                next_part = daypart(node.get('begin'))
+
+                if next_part.ffmpeg_param != part.ffmpeg_param:
+                    # encoder restarts only between two clips
+                    messenger.info(f'Switch encoder to daypart '
+                                   f'"{next_part.name}"')
+                    try:
+                        _ff.encoder.stdin.close()
+                    except BrokenPipeError:
+                        pass
+
+                    _ff.encoder.wait()
+                    part = next_part
+                    enc_cmd = encoder_cmd(streams + overlay, part)
+                    _ff.encoder = start_encoder(enc_cmd)
+
+                messenger.info(f'Play: {node.get("source")}',
+                               **clip_meta(node))
+
+                dec_cmd = [
+                    'ffmpeg', '-v', _log.ff_level.lower(),
+                    '-hide_banner', '-nostats'
+                    ] + node['src_cmd'] + node['filter'] + ff_pre_settings
+
+                messenger.debug(f'Decoder CMD: "{" ".join(dec_cmd)}"')
+
+                with Popen(dec_cmd, stdout=PIPE, stderr=PIPE) as _ff.decoder:
+                    dec_err_thread = Thread(target=ffmpeg_stderr_reader,
+                                            args=(_ff.decoder.stderr, True))
+                    dec_err_thread.daemon = True
+                    dec_err_thread.start()
+
+                    while True:
+                        buf = _ff.decoder.stdout.read(COPY_BUFSIZE)
+                        if not buf or not write_or_reconnect(buf, enc_cmd):
+                            break
+
+        except SystemExit:
+            messenger.info('Got close command')
+            terminate_processes(watcher)
+
+        except KeyboardInterrupt:
+            messenger.warning('Program terminated')
+            terminate_processes(watcher)
+
+        # close encoder when nothing is to do anymore
+        if _ff.encoder.poll() is None:
+            _ff.encoder.terminate()
+
+    finally:
+        if _ff.encoder.poll() is None:
+            _ff.encoder.terminate()
+        _ff.encoder.wait()
@@ -3,15 +3,71 @@ from subprocess import PIPE, Popen
 from threading import Thread
 
 from ffplayout.folder import GetSourceFromFolder, MediaStore, MediaWatcher
+from ffplayout.metrics import metrics_server
 from ffplayout.playlist import GetSourceFromPlaylist
+from ffplayout.rpc import rpc_server
 from ffplayout.utils import (_ff, _log, _playlist, _playout, _pre, _text,
-                             ffmpeg_stderr_reader, get_date, messenger,
-                             pre_audio_codec, stdin_args, terminate_processes)
+                             clip_meta, daypart, ffmpeg_stderr_reader,
+                             get_date, messenger, pre_audio_codec, stdin_args,
+                             terminate_processes)
 
 _WINDOWS = os.name == 'nt'
 COPY_BUFSIZE = 1024 * 1024 if _WINDOWS else 65424
 
 
+def language_outputs(overlay, metadata, ffmpeg_param):
+    """
+    one output for every audio language, with the same video,
+    the text overlay can only be in the first output,
+    because the zmq address can be bound only one time
+    """
+    outputs = []
+
+    if len(_playout.language_outputs) > len(_pre.audio_languages):
+        messenger.warning(
+            f'{len(_playout.language_outputs)} language outputs, but only '
+            f'{len(_pre.audio_languages)} audio languages, the rest is '
+            'ignored!')
+
+    for num, (lang, target) in enumerate(
+            zip(_pre.audio_languages, _playout.language_outputs)):
+        messenger.debug(f'Output {num + 1} has audio language "{lang}"')
+        outputs += ['-map', '0:v', '-map', f'0:a:{num}'] + (
+            overlay if num == 0 else []) + metadata + \
+            ffmpeg_param + target
+
+    return outputs
+
+
+def start_encoder(overlay, metadata, part):
+    """
+    start encoder with the ffmpeg params from the daypart
+    """
+    if _playout.language_outputs:
+        outputs = language_outputs(overlay, metadata, part.ffmpeg_param)
+    else:
+        outputs = overlay + metadata + part.ffmpeg_param + \
+            _playout.stream_output
+
+    enc_cmd = [
+        'ffmpeg', '-v', _log.ff_level.lower(), '-hide_banner',
+        '-nostats'
+        ] + (['-re'] if _playout.realtime else []) + [
+            '-thread_queue_size', '160', '-i', 'pipe:0'
+        ] + outputs
+
+    messenger.debug(f'Encoder CMD: "{" ".join(enc_cmd)}"')
+
+    encoder = Popen(enc_cmd, stdin=PIPE, stderr=PIPE)
+
+    enc_err_thread = Thread(target=ffmpeg_stderr_reader,
+                            args=(encoder.stderr, False))
+    enc_err_thread.daemon = True
+    enc_err_thread.start()
+
+    return encoder
+
+
 def output():
     """
     this output is for streaming to a target address,
@@ -38,28 +94,21 @@ def output():
                 _text.address.replace(':', '\\:'), _text.fontfile)
         ]
 
-    try:
-        enc_cmd = [
-            'ffmpeg', '-v', _log.ff_level.lower(), '-hide_banner',
-            '-nostats', '-re', '-thread_queue_size', '160', '-i', 'pipe:0'
-            ] + overlay + [
-                '-metadata', 'service_name=' + _playout.name,
-                '-metadata', 'service_provider=' + _playout.provider,
-                '-metadata', f'year={year}'
-            ] + _playout.ffmpeg_param + _playout.stream_output
+    metadata = [
+        '-metadata', 'service_name=' + _playout.name,
+        '-metadata', 'service_provider=' + _playout.provider,
+        '-metadata', f'year={year}'
+    ]
 
-        messenger.debug(f'Encoder CMD: "{" ".join(enc_cmd)}"')
-
-        _ff.encoder = Popen(enc_cmd, stdin=PIPE, stderr=PIPE)
-
-        enc_err_thread = Thread(target=ffmpeg_stderr_reader,
-                                args=(_ff.encoder.stderr, False))
-        enc_err_thread.daemon = True
-        enc_err_thread.start()
+    try:
+        part = daypart()
+        _ff.encoder = start_encoder(overlay, metadata, part)
 
         if _playlist.mode and not stdin_args.folder:
             watcher = None
             get_source = GetSourceFromPlaylist()
+            rpc_server(get_source)
+            metrics_server(get_source)
         else:
             messenger.info('Start folder mode')
             media = MediaStore()
@@ -71,7 +120,19 @@ def output():
                 if watcher is not None:
                     watcher.current_clip = node.get('source')
 
-                messenger.info(f'Play: {node.get("source")}')
+                next_part = daypart(node.get('begin'))
+
+                if next_part.ffmpeg_param != part.ffmpeg_param:
+                    # encoder restarts only between two clips
+                    messenger.info(f'Switch encoder to daypart '
+                                   f'"{next_part.name}"')
+                    _ff.encoder.stdin.close()
+                    _ff.encoder.wait()
+                    part = next_part
+                    _ff.encoder = start_encoder(overlay, metadata, part)
+
+                messenger.info(f'Play: {node.get("source")}',
+                               **clip_meta(node))
 
                 dec_cmd = [
                     'ffmpeg', '-v', _log.ff_level.lower(),
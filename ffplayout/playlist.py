@@ -17,20 +17,253 @@
 
 # ------------------------------------------------------------------------------
 
+import codecs
+import gzip
+import hashlib
+import json
 import os
+import re
+import shutil
 import socket
 import time
+from concurrent.futures import ThreadPoolExecutor
 from copy import deepcopy
-from datetime import timedelta
+from datetime import datetime, timedelta, timezone
+from difflib import get_close_matches
+from email.utils import parsedate_to_datetime
+from functools import partial
+from glob import glob
+from itertools import count, repeat
 from math import isclose
-from threading import Thread
+from random import Random
+from subprocess import TimeoutExpired
+from threading import Lock, RLock, Semaphore, Thread
+from types import SimpleNamespace
+from urllib.parse import urljoin, urlparse
 
 import requests
+from urllib3 import disable_warnings
+from urllib3.exceptions import InsecureRequestWarning
+from urllib3.util.request import ACCEPT_ENCODING
 
 from .filters.default import build_filtergraph
-from .utils import (MediaProbe, _general, _playlist, check_sync, get_date,
-                    get_delta, get_float, get_time, messenger, src_or_dummy,
-                    stdin_args, valid_json)
+from .utils import (DUMMY_LEN, MediaProbe, _ff, _general, _global, _playlist,
+                    _playout, _pre, _rpc, _storage, _watchdog,
+                    alternate_source, check_sync, clip_speed, dst_change,
+                    get_date, get_delta, get_float, get_now, get_time,
+                    get_utc_offset, gunzip, is_advertisement, is_gzip, is_live,
+                    is_terminated, json_error, load_config, messenger, off_air,
+                    parse_playlist, parse_scale, playlist_file,
+                    playlist_format, preroll, probe_cache, read_json_streaming,
+                    read_m3u, read_stdin, read_yaml, run_hook, sec_to_time,
+                    src_or_dummy, stdin_args, valid_json, vfr_info)
+
+# how many clips are probed at the same time in playlist validation
+VALIDATE_WORKERS = 8
+# how many HEAD requests to remote clips run at the same time, and how long
+# they can take, so validating a big playlist don't overload the server
+REMOTE_CHECKS = Semaphore(2)
+REMOTE_CHECK_TIMEOUT = 3
+# clips which are this factor smaller or bigger then the output,
+# get a warning in validation, because scaling is visible
+SCALE_WARN_FACTOR = 2
+# block size to read clips for checksum
+CHECKSUM_CHUNK = 1024 * 1024
+# remote clips with checksum are downloaded in validation, without
+# saving them, bigger ones are not checked
+REMOTE_CHECKSUM_BYTES = 4 * 1024 * 1024 * 1024
+# json playlists from this size on are read clip by clip, so the file
+# content is not in memory next to the clips, for gzip the packed size
+STREAM_BYTES = 16 * 1024 * 1024
+# how deep playlists can include other playlists, against include cycles
+INCLUDE_DEPTH = 4
+# seconds between the summaries from a repeated playlist error
+REPEAT_LOG_INTERVAL = 300
+# when the playout starts in the last seconds of a clip,
+# the next clip is taken, instead of playing only its end
+RESUME_END_GAP = 3
+# a live source is not connected again, when less seconds are left,
+# and counts its attempts new, when it played this seconds
+LIVE_MIN_REST = 1
+LIVE_STABLE = 30
+# content encodings from remote playlists, which get decoded,
+# brotli is only there, when the brotli module is installed
+DECODED_ENCODINGS = ['', 'identity'] + ACCEPT_ENCODING.split(',')
+# standby sources with this extensions are read as playlist,
+# other sources are a clip, which is played in a loop
+PLAYLIST_EXTENSIONS = ('.json', '.yml', '.yaml', '.m3u', '.m3u8', '.gz')
+
+if _playlist.accept_invalid_certs:
+    # it is wanted, so don't warn on every request
+    disable_warnings(InsecureRequestWarning)
+
+# known clip fields with their types, other fields are allowed,
+# for custom filters, but names close to known ones get a warning
+NUMBER = (int, float)
+CLIP_FIELDS = {
+    'in': NUMBER,
+    'out': NUMBER,
+    'duration': NUMBER,
+    'source': str,
+    'category': str,
+    'tags': list,
+    'title': str,
+    'audio_track': int,
+    'audio_tracks': dict,
+    'fade_in': NUMBER,
+    'fade_out': NUMBER,
+    'crossfade': bool,
+    'loudnorm': bool,
+    'loop_count': int,
+    'skippable': bool,
+    'mute': bool,
+    'mode': str,
+    'text': str,
+    'text_x': (str, int, float),
+    'text_y': (str, int, float),
+    'text_size': NUMBER,
+    'text_font': str,
+    'text_color': str,
+    'begin': (str, int, float),
+    'hard_start': (str, int, float),
+    'input_args': list,
+    'sha256': str,
+    'md5': str,
+    'shuffle_group': str,
+    'scale': str,
+    'subtitle': str,
+    'speed': NUMBER,
+    'max_duration': NUMBER,
+    'live': bool,
+    'sources': list
+}
+
+
+class PlayoutStats:
+    """
+    counters from validation and remote requests, for metrics,
+    and the report from the last validation
+    """
+
+    def __init__(self):
+        self.lock = Lock()
+        self.report = None
+        self.values = {
+            'validation_warnings': 0,
+            'validation_errors': 0,
+            'remote_success': 0,
+            'remote_failure': 0
+        }
+
+    def add(self, name, value=1):
+        with self.lock:
+            self.values[name] += value
+
+    def reset(self, *names):
+        with self.lock:
+            for name in names:
+                self.values[name] = 0
+
+    def get(self):
+        with self.lock:
+            return dict(self.values)
+
+
+stats = PlayoutStats()
+
+
+class RepeatedLog:
+    """
+    log errors from a failing playlist source only at the first time,
+    repeats are counted and summarized every REPEAT_LOG_INTERVAL seconds,
+    so a long outage don't flood the log and the mails
+    """
+
+    def __init__(self):
+        self.lock = Lock()
+        self.sources = {}
+
+    def error(self, source, msg):
+        with self.lock:
+            now = time.monotonic()
+            entry = self.sources.get(source)
+
+            if entry is None:
+                self.sources[source] = SimpleNamespace(count=0, logged=now)
+                count = 0
+            else:
+                entry.count += 1
+
+                if now - entry.logged < REPEAT_LOG_INTERVAL:
+                    return
+
+                count = entry.count
+                entry.count = 0
+                entry.logged = now
+
+        if count:
+            msg = f'Still failing ({count} times): {msg}'
+
+        messenger.error(msg, current_file=source)
+
+    def reset(self, source):
+        """
+        source works again, the next error gets logged directly
+        """
+        with self.lock:
+            entry = self.sources.pop(source, None)
+
+        if entry is not None:
+            messenger.info('Playlist source works again: {current_file}',
+                           current_file=source)
+
+
+repeated = RepeatedLog()
+
+
+class ValidationReport:
+    """
+    problems from playlist validation, counted by severity and listed
+    with clip index and source, for callers which need more then log lines
+    """
+
+    def __init__(self, list_date=None):
+        self.lock = Lock()
+        self.list_date = list_date
+        self.issues = []
+
+    def add(self, severity, index, source, reason):
+        with self.lock:
+            self.issues.append({
+                'severity': severity,
+                'index': index,
+                'source': source,
+                'reason': reason
+            })
+
+        stats.add(f'validation_{severity}s')
+
+    def warning(self, index, source, reason):
+        self.add('warning', index, source, reason)
+
+    def error(self, index, source, reason):
+        self.add('error', index, source, reason)
+
+    def count(self, severity):
+        with self.lock:
+            return sum(1 for i in self.issues if i['severity'] == severity)
+
+    def to_dict(self):
+        with self.lock:
+            issues = sorted(self.issues, key=lambda i: (
+                i['index'] is None, i['index'] or 0))
+
+        return {
+            'date': self.list_date,
+            'warnings': self.count('warning'),
+            'errors': self.count('error'),
+            'issues': issues
+        }
 
 
 def handle_list_init(node):
@@ -40,7 +273,9 @@ def handle_list_init(node):
     """
     messenger.debug('List init')
 
-    delta, total_delta = get_delta(node['begin'])
+    # clip time goes faster or slower then play time, with speed
+    speed = clip_speed(node)
+    delta, total_delta = (d * speed for d in get_delta(node['begin']))
     seek = abs(delta) + node['seek'] if abs(delta) + node['seek'] >= 1 else 0
 
     if node['out'] - seek > total_delta:
@@ -48,6 +283,12 @@ def handle_list_init(node):
     else:
         out = node['out']
 
+    if seek and out - seek < RESUME_END_GAP:
+        messenger.info(
+            f'Clip ends in {out - seek:.2f} seconds, skip:\n{node["source"]}')
+
+        return None
+
     if out - seek > 1:
         node['out'] = out
         node['seek'] = seek
@@ -67,228 +308,2593 @@ def handle_list_end(duration, node):
     """
     messenger.debug('List end')
 
-    out = node['seek'] + duration if node['seek'] > 0 else duration
+    out = node['seek'] + duration if node['seek'] > 0 else duration
+
+    # prevent looping
+    if out > node['duration']:
+        out = node['duration']
+    else:
+        messenger.warning(
+            f'Clip length is not in time, new duration is: {duration:.2f}')
+
+    if node['duration'] > duration > 1 and \
+            node['duration'] - node['seek'] >= duration:
+        node['out'] = out
+        node = src_or_dummy(node)
+    elif node['duration'] > duration > 0.0:
+        messenger.warning(
+            f'Last clip less then 1 second long, skip:\n{node["source"]}')
+        node = None
+    else:
+        missing_secs = abs(duration - (node['duration'] - node['seek']))
+        messenger.error(
+            f'Playlist is not long enough:\n{missing_secs:.2f} seconds needed')
+        out = node['out']
+        node = src_or_dummy(node)
+
+    return node
+
+
+def timed_source(node, last):
+    """
+    prepare input clip
+    check begin and length from clip
+    return clip only if we are in 24 hours time range
+    """
+    delta, total_delta = get_delta(node['begin'])
+    node_ = None
+
+    if not stdin_args.loop and _playlist.length and _playout.realtime:
+        messenger.debug(f'delta: {delta:f}')
+        messenger.debug(f'total_delta: {total_delta:f}')
+        check_sync(delta)
+
+    # in clip time, with speed
+    total_delta *= clip_speed(node)
+
+    if (total_delta > node['out'] - node['seek'] and not last) \
+            or stdin_args.loop or not _playlist.length \
+            or not _playout.realtime:
+        # when we are in the 24 houre range, get the clip
+        node_ = src_or_dummy(node)
+
+    elif total_delta <= 0:
+        messenger.info(f'Begin is over play time, skip:\n{node["source"]}')
+
+    elif total_delta < node['duration'] - node['seek'] or last:
+        node_ = handle_list_end(total_delta, node)
+
+    return node_
+
+
+def loop_count(node):
+    """
+    how often the clip is played in a row
+    """
+    return max(int(get_float(node.get('loop_count'), 1)), 1)
+
+
+def max_length(node):
+    """
+    longest play time for the clip, from clip or config, against live
+    sources with a wrong out value, None when there is no limit
+    """
+    guard = get_float(node.get('max_duration', _playlist.max_duration), 0)
+
+    return guard if guard > 0 else None
+
+
+def clip_values(node):
+    """
+    values which define a clip in the playlist
+    """
+    return (node.get('source'), get_float(node.get('in'), 0),
+            get_float(node.get('out'), 0), get_float(node.get('duration'), 0))
+
+
+def check_length(total_play_time, list_date, last_node, report):
+    """
+    check if playlist is long enough, or to long,
+    return False when it is not long enough
+    """
+    if not _playlist.length or stdin_args.loop:
+        return True
+
+    delta = total_play_time - _playlist.length
+    source = last_node.get('source') if last_node else None
+    length = (f'Total play time is: {timedelta(seconds=total_play_time)}, '
+              f'target length is: {timedelta(seconds=_playlist.length)}')
+
+    if delta < -_playlist.length_tolerance:
+        messenger.error(
+            f'Playlist from {list_date} is not long enough!\n{length}, '
+            f'{abs(delta):.2f} seconds missing after last clip: "{source}"'
+        )
+        report.error(None, source, f'Playlist is not long enough! {length}')
+        return False
+    elif delta > _playlist.length_tolerance:
+        messenger.warning(
+            f'Playlist from {list_date} is to long!\n{length}, '
+            f'{delta:.2f} seconds over, last clip: "{source}"'
+        )
+        report.warning(None, source, f'Playlist is to long! {length}')
+
+    return True
+
+
+def check_dst(list_date, report):
+    """
+    warn when the playlist day has a daylight saving time change,
+    the playout follows the wall clock, so an hour is skipped or repeated
+    """
+    change = dst_change(list_date)
+
+    if not change:
+        return
+
+    seconds, moment = change
+    shift = timedelta(seconds=abs(seconds))
+
+    if seconds > 0:
+        reason = (f'the clock goes forward {shift} to '
+                  f'{moment.strftime("%Y-%m-%d %H:%M")}, the day is {shift} '
+                  'shorter and the clips in between are skipped!')
+    else:
+        reason = (f'the clock goes back {shift} at '
+                  f'{moment.strftime("%Y-%m-%d %H:%M")}, the day is {shift} '
+                  'longer and the clips before are played again!')
+
+    messenger.warning(f'Playlist from {list_date} has a daylight saving '
+                      f'time change, {reason}')
+    report.warning(None, None, f'Daylight saving time change, {reason}')
+
+
+class RedirectError(requests.exceptions.RequestException):
+    """
+    redirect to other host, when only the same host is allowed
+    """
+
+
+def remote_session(url):
+    """
+    http session for remote playlists and sources,
+    with redirect and certificate settings from config
+    """
+    session = requests.Session()
+    session.max_redirects = _playlist.remote_redirects
+    session.verify = not _playlist.accept_invalid_certs
+    host = urlparse(url).hostname
+
+    def check_redirect(response, *args, **kwargs):
+        if _playlist.remote_same_host and response.is_redirect:
+            target = urljoin(response.url, response.headers['location'])
+
+            if urlparse(target).hostname != host:
+                raise RedirectError(f'redirect to other host: {target}')
+
+    session.hooks['response'].append(check_redirect)
+
+    return session
+
+
+def read_limited(result):
+    """
+    read the body from a streamed response in parts, it is decoded
+    from gzip, deflate or brotli, so the limit is for the decoded size,
+    return the size when it is bigger then max_playlist_bytes
+    """
+    limit = int(_playlist.max_playlist_bytes)
+    length = get_float(result.headers.get('content-length'), 0)
+    encoding = (result.headers.get('content-encoding') or '').lower()
+    body = bytearray()
+
+    if any(e.strip() not in DECODED_ENCODINGS for e in encoding.split(',')):
+        result.close()
+        raise requests.exceptions.ContentDecodingError(
+            'encoding is not supported')
+
+    if length > limit:
+        result.close()
+        return int(length)
+
+    for chunk in result.iter_content(chunk_size=65536):
+        body.extend(chunk)
+
+        if len(body) > limit:
+            result.close()
+            return len(body)
+
+    # the body is taken from here for content and text
+    result._content = bytes(body)
+
+    return None
+
+
+def http_time(value):
+    """
+    timestamp from http date header, like Last-Modified, which is in GMT,
+    return None when the header is missing or malformed
+    """
+    if not value:
+        return None
+
+    try:
+        stamp = parsedate_to_datetime(value)
+    except (TypeError, ValueError, IndexError):
+        return None
+
+    if stamp is None:
+        return None
+
+    if stamp.tzinfo is None:
+        stamp = stamp.replace(tzinfo=timezone.utc)
+
+    return stamp.timestamp()
+
+
+def check_remote(source, index, report):
+    """
+    send HEAD request to remote clip source,
+    to know before it goes on air, when the link is dead
+    """
+    with REMOTE_CHECKS:
+        if is_terminated.is_set():
+            return
+
+        try:
+            with remote_session(source) as session:
+                result = session.head(source, timeout=REMOTE_CHECK_TIMEOUT,
+                                      allow_redirects=True)
+            error = None if result.status_code < 300 else \
+                f'status code {result.status_code}'
+        except requests.exceptions.RequestException as err:
+            error = err
+
+    if error:
+        messenger.warning(
+            f'Clip {index} from url: "{source}" is not reachable: {error}')
+        report.warning(index, source, f'Url is not reachable: {error}')
+
+
+def check_fields(nodes, json_file, strict=False):
+    """
+    check structure and value types from playlist, log clip number
+    and field from wrong values, return False when the playlist
+    can not be used, with strict also when a value is wrong
+    """
+    program = nodes.get('program')
+
+    if not isinstance(program, list):
+        messenger.error('Playlist {current_file} has no program list',
+                        current_file=json_file)
+        return False
+
+    errors = []
+    usable = True
+
+    for index, node in enumerate(program, 1):
+        if not isinstance(node, dict):
+            errors.append(f'Clip {index} is not an object: {node}')
+            usable = False
+            continue
+
+        for key, value in node.items():
+            if key in ['in', 'out', 'duration'] and isinstance(value, str):
+                # timecode from editing tools, like 00:03:45.000
+                sec = parse_timecode(value)
+
+                if sec is None:
+                    errors.append(
+                        f'Clip {index} field "{key}" needs seconds or '
+                        f'HH:MM:SS.mmm: {value!r}')
+                    continue
+
+                node[key] = value = sec
+
+            types = CLIP_FIELDS.get(key)
+
+            if types is None:
+                match = get_close_matches(key, CLIP_FIELDS, 1, 0.75)
+
+                if match:
+                    messenger.warning(
+                        f'Clip {index} in {{current_file}} has unknown '
+                        f'field "{key}", did you mean "{match[0]}"?',
+                        current_file=json_file)
+            elif value is not None and (
+                    not isinstance(value, types)
+                    or isinstance(value, bool) and types is not bool):
+                errors.append(
+                    f'Clip {index} field "{key}" has wrong value: {value!r}')
+            elif key in ['tags', 'input_args', 'sources'] \
+                    and value is not None \
+                    and not all(isinstance(item, str) for item in value):
+                errors.append(
+                    f'Clip {index} field "{key}" needs strings: {value!r}')
+            elif key in ['begin', 'hard_start'] and value is not None \
+                    and parse_begin(value) is None:
+                errors.append(
+                    f'Clip {index} field "{key}" needs HH:MM:SS: {value!r}')
+            elif key == 'speed' and value is not None and value <= 0:
+                errors.append(
+                    f'Clip {index} field "speed" needs a positive number: '
+                    f'{value!r}')
+            elif key == 'scale' and value is not None \
+                    and parse_scale(value) is None:
+                errors.append(
+                    f'Clip {index} field "scale" needs WxH, not bigger '
+                    f'then {_pre.w}x{_pre.h}: {value!r}')
+
+    if errors:
+        messenger.error('Playlist {current_file} has wrong values:\n'
+                        + '\n'.join(errors), current_file=json_file)
+
+    return usable and not (strict and errors)
+
+
+def check_duration(node, index, probe, report):
+    """
+    compare playlist values with real clip duration, a clip which is
+    shorter then out lets the timeline drift, with clamp_out
+    the out value is set to the real duration
+    """
+    if not probe.format or not probe.format.get('duration') \
+            or loop_count(node) > 1:
+        return
+
+    real = float(probe.format['duration'])
+    _out = get_float(node.get('out'), 0)
+    duration = get_float(node.get('duration'), 0)
+    source = node.get('source')
+
+    if _out - real > _playlist.duration_tolerance:
+        messenger.warning(
+            f'Clip {index} is shorter then out: "{source}", '
+            f'expected: {_out:.2f}, real duration: {real:.2f}')
+        report.warning(index, source, f'Clip is shorter then out, expected: '
+                       f'{_out:.2f}, real duration: {real:.2f}')
+
+        if _playlist.clamp_out:
+            node['clamp_out'] = real
+    elif abs(duration - real) > _playlist.duration_tolerance:
+        messenger.warning(
+            f'Clip {index} duration does not match: "{source}", '
+            f'expected: {duration:.2f}, real duration: {real:.2f}')
+        report.warning(index, source, f'Duration does not match, expected: '
+                       f'{duration:.2f}, real duration: {real:.2f}')
+
+
+def check_resolution(node, index, probe, report):
+    """
+    warn when the clip resolution is much smaller or bigger
+    then the output, so it is clear that the clip gets scaled
+    """
+    if not probe.video or not probe.video[0] \
+            or not probe.video[0].get('width') \
+            or not probe.video[0].get('height'):
+        return
+
+    width = int(probe.video[0]['width'])
+    height = int(probe.video[0]['height'])
+    target = parse_scale(node.get('scale')) or (_pre.w, _pre.h)
+    factor = max(width / target[0], target[0] / width,
+                 height / target[1], target[1] / height)
+
+    if factor >= SCALE_WARN_FACTOR:
+        messenger.warning(
+            f'Clip {index} has resolution {width}x{height}, it gets scaled '
+            f'to {target[0]}x{target[1]}: "{node.get("source")}"')
+        report.warning(index, node.get('source'),
+                       f'Resolution {width}x{height} gets scaled to '
+                       f'{target[0]}x{target[1]}')
+
+
+def remote_checksum(source, algorithm, session=remote_session):
+    """
+    checksum from remote clip, it is downloaded in parts, up to
+    REMOTE_CHECKSUM_BYTES, return checksum and None, or None and the
+    reason why it is not checked
+    """
+    checksum = hashlib.new(algorithm)
+    size = 0
+
+    with REMOTE_CHECKS:
+        try:
+            with session(source) as remote, remote.get(
+                    source, timeout=REMOTE_CHECK_TIMEOUT,
+                    stream=True) as result:
+                if result.status_code >= 300:
+                    return None, f'status code {result.status_code}'
+
+                for chunk in result.iter_content(chunk_size=CHECKSUM_CHUNK):
+                    if is_terminated.is_set():
+                        return None, 'playout goes down'
+
+                    size += len(chunk)
+
+                    if size > REMOTE_CHECKSUM_BYTES:
+                        return None, ('file is bigger then '
+                                      f'{REMOTE_CHECKSUM_BYTES} bytes')
+
+                    checksum.update(chunk)
+        except requests.exceptions.RequestException as err:
+            return None, err
+
+    return checksum.hexdigest(), None
+
+
+def check_checksum(node, index, probe, report, session=remote_session):
+    """
+    compare checksum from clip with the file, a clip with wrong
+    checksum is broken and gets replaced by the filler when it plays,
+    remote clips over http are downloaded for it,
+    return the algorithm with wrong checksum, or None
+    """
+    source = node.get('source')
+
+    for algorithm in ['sha256', 'md5']:
+        expected = node.get(algorithm)
+
+        if not expected:
+            continue
+
+        if probe.is_remote:
+            if not source.lower().startswith(('http://', 'https://')):
+                reason = 'only http sources are downloaded'
+                checksum = None
+            else:
+                checksum, reason = remote_checksum(source, algorithm,
+                                                   session)
+
+            if checksum is None:
+                messenger.warning(
+                    f'Clip {index} has {algorithm} checksum, but it is not '
+                    f'checked, {reason}: "{source}"')
+                report.warning(index, source,
+                               f'Remote {algorithm} not checked: {reason}')
+                return None
+        elif not source or not os.path.isfile(source):
+            return None
+        else:
+            hashed = hashlib.new(algorithm)
+
+            with open(source, 'rb') as f:
+                for chunk in iter(lambda: f.read(CHECKSUM_CHUNK), b''):
+                    hashed.update(chunk)
+
+            checksum = hashed.hexdigest()
+
+        if checksum != expected.strip().lower():
+            return algorithm
+
+    return None
+
+
+def check_node(node, index, report):
+    """
+    validate values from a single clip
+    and test if source path exist
+    """
+    missing = []
+
+    if is_terminated.is_set():
+        return missing
+
+    probe = MediaProbe()
+    probe.load(node.get('source'))
+    # a missing source is only reported, when no alternate plays
+    source, probe = alternate_source(node, probe)
+
+    if source != node.get('source'):
+        messenger.info(f'Clip {index} plays alternate source, because '
+                       f'"{node.get("source")}" is missing: "{source}"')
+
+    _in = get_float(node.get('in'), 0)
+    _out = get_float(node.get('out'), 0)
+    duration = get_float(node.get('duration'), 0)
+
+    check_duration(node, index, probe, report)
+    algorithm = check_checksum(node, index, probe, report)
+
+    if algorithm:
+        node['checksum_failed'] = True
+        missing.append(f'Clip {index} has wrong {algorithm} checksum, '
+                       f'the file is changed or broken: "{source}"')
+
+    if probe.is_remote:
+        if node.get('mode') == 'audio':
+            if not probe.audio:
+                missing.append(f'Remote file has no audio: "{source}"')
+        elif not probe.video or not probe.video[0]:
+            missing.append(f'Remote file not exist: "{source}"')
+
+        if _playlist.check_remote:
+            check_remote(probe.src, index, report)
+    elif source is None or not os.path.isfile(source):
+        missing.append(f'File not exist: "{source}"' + (
+            ', also no alternate source' if node.get('sources') else ''))
+
+    if not type(node.get('in')) in [int, float]:
+        missing.append(f'No in Value in: "{node}"')
+
+    if _out == 0:
+        missing.append(f'No out Value in: "{node}"')
+
+    if duration == 0:
+        missing.append(f'No duration Value in: "{node}"')
+
+    if _out != 0 and _out <= _in:
+        missing.append(f'Clip {index} has out ({_out}) not after '
+                       f'in ({_in}): "{source}"')
+
+    if _in < 0:
+        missing.append(f'Clip {index} has negative in ({_in}): "{source}"')
+    elif probe.format and probe.format.get('duration'):
+        real = float(probe.format['duration'])
+
+        # seeking to the end gives only black
+        if _in > 0 and _in >= real - _playlist.duration_tolerance:
+            missing.append(f'Clip {index} has in ({_in}) near or after the '
+                           f'end, real duration: {real:.2f}: "{source}"')
+
+    vfr = vfr_info(probe) if _pre.vfr_check else None
+
+    if vfr:
+        rates = f'real: {vfr[0]:.3f} fps, average: {vfr[1]:.3f} fps'
+
+        if _pre.vfr_fix:
+            rates += f', it is normalized to {_pre.fps} fps'
+
+        messenger.warning(
+            f'Clip {index} has variable frame rate, {rates}: "{source}"')
+        report.warning(index, source, f'Variable frame rate, {rates}')
+
+    guard = max_length(node)
+    length = (_out - _in) * loop_count(node) / clip_speed(node)
+
+    if guard is not None and length > guard:
+        messenger.warning(
+            f'Clip {index} plays {length:.2f} seconds, it is cut at '
+            f'max_duration {guard:.2f}: "{source}"')
+        report.warning(index, source,
+                       f'Longer then max_duration, cut at {guard:.2f}')
+
+    check_resolution(node, index, probe, report)
+    subtitle = node.get('subtitle')
+
+    if subtitle and not os.path.isfile(subtitle):
+        messenger.warning(
+            f'Clip {index} has no subtitle file "{subtitle}": "{source}"')
+        report.warning(index, source, f'Subtitle not exists: "{subtitle}"')
+    input_args = node.get('input_args')
+
+    if input_args and _playlist.allow_input_args:
+        messenger.info(f'Clip {index} has input args: {" ".join(input_args)}')
+    elif input_args:
+        messenger.warning(
+            f'Clip {index} has input args, but they are not allowed '
+            f'in config, they are ignored: "{source}"')
+        report.warning(index, source, 'Input args are not allowed')
+
+    mode = node.get('mode')
+
+    if mode is not None and mode not in ['video', 'audio', 'both']:
+        missing.append(f'Clip {index} has mode "{mode}", it can be video, '
+                       f'audio or both: "{source}"')
+    elif mode == 'audio' and not (_pre.audio_background
+                                  and os.path.isfile(_pre.audio_background)):
+        messenger.warning(f'Clip {index} is audio only, but no '
+                          f'audio_background exists, black is shown: '
+                          f'"{source}"')
+        report.warning(index, source, 'Audio only without audio_background')
+
+    tracks = [node.get('audio_track')]
+
+    if isinstance(node.get('audio_tracks'), dict):
+        tracks += list(node['audio_tracks'].values())
+
+    for track in tracks:
+        if track is not None and probe.audio \
+                and probe.audio[0] is not None \
+                and (type(track) is not int
+                     or not 0 <= track < len(probe.audio)):
+            missing.append(f'Clip {index} has no audio track {track}, '
+                           f'only {len(probe.audio)} found: "{source}"')
+
+    if duration != 0 and duration < _out and 'clamp_out' not in node:
+        missing.append(f'Clip {index} has out ({_out}) bigger then '
+                       f'duration ({duration}): "{source}"')
+
+    return missing
+
+
+def write_back(nodes, json_file):
+    """
+    write corrected playlist to its local file,
+    the original file is kept as backup
+    """
+    backup = json_file + '.bak'
+    temp = json_file + '.tmp'
+
+    for node in nodes['program']:
+        if 'begin_at' in node:
+            node['begin'] = node.pop('begin_at')
+
+        for key in ['source', 'sources', 'subtitle']:
+            if f'{key}_raw' in node:
+                node[key] = node.pop(f'{key}_raw')
+
+    try:
+        shutil.copy2(json_file, backup)
+
+        with open(temp, 'w', encoding='utf-8') as f:
+            json.dump(nodes, f, indent=4)
+
+        os.replace(temp, json_file)
+    except OSError as err:
+        messenger.error(
+            f'Corrected playlist {{current_file}} not written\n{err}',
+            current_file=json_file)
+        return
+
+    messenger.info(
+        f'Write corrected playlist {{current_file}}, backup is: "{backup}"',
+        current_file=json_file)
+
+
+def check_json(clip_nodes, list_date, program=None, write_file=None,
+               report=None):
+    """
+    validate json values and test if source paths exist,
+    clamped out values are taken over to program,
+    and are written to write_file, when it is set,
+    all problems are collected in report,
+    return False when the playlist has errors
+    """
+    error = ''
+    clamped = False
+    report = report or ValidationReport(list_date)
+    stats.reset('validation_warnings', 'validation_errors')
+
+    # check if all values are valid, the clips are probed in parallel,
+    # but the results come back in playlist order
+    with ThreadPoolExecutor(max_workers=VALIDATE_WORKERS) as executor:
+        results = executor.map(check_node, clip_nodes['program'], count(1),
+                               repeat(report))
+
+        for index, (node, missing) in enumerate(
+                zip(clip_nodes['program'], results)):
+            if node.pop('checksum_failed', False) and program is not None:
+                program[index]['checksum_failed'] = True
+
+            if 'clamp_out' in node:
+                node['out'] = node['duration'] = node.pop('clamp_out')
+                clamped = True
+
+                if program is not None:
+                    program[index]['out'] = node['out']
+                    program[index]['duration'] = node['out']
+
+            for reason in missing:
+                report.error(index + 1, node.get('source'), reason)
+
+            line = '\n'.join(missing)
+            if line:
+                error += line + f'\nIn line: {node}\n\n'
+
+    if is_terminated.is_set():
+        return True
+
+    if error:
+        messenger.error(
+            'Validation error, check JSON playlist, '
+            f'values are missing or malformed:\n{error}'
+        )
+
+    # malformed clips are not allowed to go back in time
+    counter = sum(length for _, _, length in timeline(clip_nodes['program']))
+    length_ok = check_length(counter, list_date, clip_nodes['program'][-1],
+                             report)
+    check_begin(clip_nodes['program'], report)
+    check_duplicates(clip_nodes['program'], report)
+    check_dst(list_date, report)
+    probe_cache.save()
+    stats.report = report
+
+    if clamped and write_file:
+        write_back(clip_nodes, write_file)
+
+    return not error and length_ok
+
+
+def expand_source(source, index, json_file):
+    """
+    expand environment variables in clip source, like ${MEDIA_ROOT},
+    unset variables are logged with the clip number
+    """
+    for match in re.finditer(r'\$(\w+|\{([^}]*)\})', source):
+        name = match.group(2) if match.group(2) is not None \
+            else match.group(1)
+
+        if name not in os.environ:
+            messenger.warning(
+                f'Clip {index} in {{current_file}} uses unset environment '
+                f'variable "{name}" in source: "{source}"',
+                current_file=json_file)
+
+    return os.path.expandvars(source)
+
+
+def parse_timecode(value):
+    """
+    seconds from timecode HH:MM:SS.mmm, or None when it is not valid
+    """
+    parts = str(value).split(':')
+
+    # the sign of a part is lost in the sum, like at -0:00:05
+    if any(part.strip().startswith(('-', '+')) for part in parts):
+        return None
+
+    try:
+        hours, minutes, seconds = parts
+        sec = int(hours) * 3600 + int(minutes) * 60 + float(seconds)
+    except ValueError:
+        return None
+
+    if sec < 0 or not 0 <= int(minutes) < 60 or not 0 <= float(seconds) < 60:
+        return None
+
+    return sec
+
+
+def parse_begin(value):
+    """
+    absolute begin from clip, as seconds or HH:MM:SS,
+    return seconds in the playlist day, or None when it is not valid
+    """
+    if isinstance(value, bool):
+        return None
+
+    if isinstance(value, (int, float)):
+        sec = float(value)
+    else:
+        sec = parse_timecode(value)
+
+    if sec is None or sec < 0:
+        return None
+
+    if sec < (_playlist.start or 0):
+        sec += 86400.0
+
+    return sec
+
+
+def fixed_begin(node):
+    """
+    fixed begin from clip, from hard_start or begin, in seconds,
+    or None when the clip has no fixed begin
+    """
+    if node.get('hard_start') is not None:
+        return parse_begin(node['hard_start'])
+
+    return parse_begin(node.get('begin_at'))
+
+
+def play_length(node):
+    """
+    length how long the clip plays, without the cut or pad from a fixed
+    begin, loops and speed are included, max_duration cuts it
+    """
+    seek = get_float(node.get('in'), 0)
+    duration = get_float(node.get('duration'), 30)
+    out = get_float(node.get('single_out', node.get('out')), duration)
+    length = max(out - seek, 0) * loop_count(node) / clip_speed(node)
+    guard = max_length(node)
+
+    if guard is not None:
+        length = min(length, guard)
+
+    return length
+
+
+def timeline(program):
+    """
+    begin time and play length from every clip, like the player loop
+    computes them, loops are included in the length,
+    a clip with begin cuts the clip before, so it starts in time,
+    with hard_start the clip before is also padded,
+    max_duration cuts the clip
+    """
+    start = _playlist.start or 0.0
+    # compensated sum, so rounding errors don't add up over long playlists
+    total = comp = 0.0
+
+    for index, node in enumerate(program):
+        begin = start + total + comp
+        length = play_length(node)
+        anchor = None
+        hard = False
+
+        if index + 1 < len(program):
+            anchor = fixed_begin(program[index + 1])
+            hard = program[index + 1].get('hard_start') is not None
+
+        if anchor is not None and (anchor < begin + length or hard):
+            length = max(anchor - begin, 0)
+
+        yield node, begin, length
+
+        if anchor is not None and anchor <= begin + length:
+            total, comp = max(anchor, begin) - start, 0.0
+        else:
+            sum_ = total + length
+
+            if abs(total) >= length:
+                comp += (total - sum_) + length
+            else:
+                comp += (length - sum_) + total
+
+            total = sum_
+
+
+def seek_to(program, play_time):
+    """
+    clip which plays at play_time, in seconds from the playlist day,
+    return index and offset in the clip, or None after the last clip,
+    at the end of a clip the next one is taken
+    """
+    for index, (node, begin, length) in enumerate(timeline(program)):
+        if play_time < begin:
+            # time is before the first clip, or in a gap
+            return index, 0.0
+
+        if play_time < begin + length:
+            return index, play_time - begin
+
+    return None
+
+
+def check_begin(program, report):
+    """
+    warn when a clip can not start at its begin, because the clips
+    before end to late or to early, or when a clip is cut away
+    """
+    for index, (node, begin, length) in enumerate(timeline(program), 1):
+        anchor = fixed_begin(node)
+
+        if anchor is not None and abs(begin - anchor) > 0.001:
+            reason = (f'can not start at {sec_to_time(anchor)}, '
+                      f'the clips before end at {sec_to_time(begin)}')
+            messenger.warning(
+                f'Clip {index} {reason}: "{node.get("source")}"')
+            report.warning(index, node.get('source'), f'Clip {reason}')
+
+        hard = index < len(program) \
+            and program[index].get('hard_start') is not None
+        planned = play_length(node)
+
+        if hard and length > 0 and abs(length - planned) > 0.001:
+            action = 'padded' if length > planned else 'cut'
+            reason = (f'is {action} by {abs(length - planned):.2f} seconds, '
+                      'for the hard start from the next clip')
+            messenger.warning(
+                f'Clip {index} {reason}: "{node.get("source")}"')
+            report.warning(index, node.get('source'), f'Clip {reason}')
+
+        if length == 0 and index < len(program) \
+                and fixed_begin(program[index]) is not None:
+            messenger.warning(
+                f'Clip {index} is cut away by the begin from the next clip: '
+                f'"{node.get("source")}"')
+            report.warning(index, node.get('source'),
+                           'Clip is cut away by the begin from the next clip')
+
+
+def check_duplicates(program, report):
+    """
+    warn about clips with the same source, directly after each other,
+    or with mode 'any' in the whole playlist, ads are only compared
+    when they follow each other, the timeline stays like it is
+    """
+    mode = _playlist.check_duplicates
+
+    if mode not in ['consecutive', 'any']:
+        return
+
+    clips = {}
+
+    for index, node in enumerate(program, 1):
+        source = node.get('source')
+
+        if index > 1 and source == program[index - 2].get('source'):
+            messenger.warning(
+                f'Clip {index} has the same source as clip {index - 1}: '
+                f'"{source}"')
+            report.warning(index, source,
+                           f'Same source as clip {index - 1}')
+
+        if mode == 'any' and not is_advertisement(node):
+            clips.setdefault(source, []).append(index)
+
+    for source, indices in clips.items():
+        # only clips with others between them, the direct ones are above
+        if len(indices) > 1 and indices[-1] - indices[0] >= len(indices):
+            numbers = ', '.join(str(i) for i in indices)
+            messenger.warning(
+                f'Clips {numbers} have the same source: "{source}"')
+            report.warning(indices[0], source,
+                           f'Same source in clips {numbers}')
+
+
+def check_encoding(json_file):
+    """
+    test if local playlist is utf-8 encoded, a BOM from windows editors
+    is skipped, return False when the playlist can not be read
+    """
+    with open(json_file, 'rb') as f:
+        data = f.read()
+
+    # utf-32 boms starts also with the utf-16 bytes, or with zero bytes
+    if data.startswith((codecs.BOM_UTF16_LE, codecs.BOM_UTF16_BE)) \
+            or b'\x00' in data[:4]:
+        messenger.error('Playlist {current_file} is UTF-16 or UTF-32 '
+                        'encoded, save it as UTF-8', current_file=json_file)
+        return False
+
+    try:
+        data.decode('utf-8-sig')
+    except UnicodeDecodeError as err:
+        messenger.error(
+            f'Playlist {{current_file}} is not UTF-8 encoded, {err.reason} '
+            f'at byte {err.start}', current_file=json_file)
+        return False
+
+    if data.startswith(codecs.BOM_UTF8):
+        messenger.warning('Playlist {current_file} starts with UTF-8 BOM, '
+                          'it is skipped', current_file=json_file)
+
+    return True
+
+
+def write_debug(reader):
+    """
+    save playlist with computed timeline to debug path,
+    for reconstructing later what was scheduled
+    """
+    program = []
+
+    for index, (node, begin, length) in enumerate(
+            timeline(reader.nodes['program'])):
+        clip = dict(node)
+        clip.update({
+            'index': index,
+            'begin': round(begin, 3),
+            'begin_time': sec_to_time(begin),
+            'length': round(length, 3)
+        })
+        program.append(clip)
+
+    debug = {
+        'date': reader.list_date,
+        'source': reader.source,
+        'loaded': get_now().isoformat(timespec='seconds'),
+        'program': program
+    }
+
+    debug_file = os.path.join(
+        _playlist.debug_path,
+        f'{reader.list_date}_{get_now().strftime("%H%M%S")}.json')
+
+    try:
+        os.makedirs(_playlist.debug_path, exist_ok=True)
+
+        with open(debug_file, 'w', encoding='utf-8') as f:
+            json.dump(debug, f, indent=4)
+    except (OSError, TypeError) as err:
+        messenger.error(f'Debug playlist "{debug_file}" not written\n{err}')
+
+
+def validate_thread(clip_nodes, list_date, program=None, write_file=None):
+    """
+    validate json values in new thread
+    and test if source paths exist
+    """
+    if clip_nodes.get('program') and len(clip_nodes.get('program')) > 0:
+        validate = Thread(name='check_json', target=check_json,
+                          args=(clip_nodes, list_date, program, write_file))
+        validate.daemon = True
+        validate.start()
+    else:
+        messenger.error('Validation error: playlist are empty')
+
+
+class PlaylistReader:
+    """
+    read playlist for one date, from local files or urls,
+    'clock' gives the timestamp for remote playlists without
+    last-modified header, 'session' makes the http session for a url,
+    both can be replaced, for testing without wall clock and network
+    """
+
+    def __init__(self, list_date, last_mod_time, validate=True, clock=None,
+                 session=None):
+        self.list_date = list_date
+        self.last_mod_time = last_mod_time
+        self.validate = validate
+        self.clock = clock or partial(get_time, 'stamp')
+        self.session = session or remote_session
+        self.nodes = None
+        self.error = False
+        # playlist source from which the nodes are loaded
+        self.source = None
+        # playlist has included other playlists
+        self.included = False
+        # playlist has sources with wildcards
+        self.globbed = False
+        # playlist has clips in shuffled order
+        self.shuffled = False
+        # playlist got clips from fill_path at the end
+        self.filled = False
+        # last parsed remote playlist, for conditional requests
+        self.cache = SimpleNamespace(url=None, etag=None, modified=None,
+                                     mod_time=0.0, nodes=None)
+
+    def get_remote(self, url, headers=None):
+        """
+        request remote playlist, on connection errors and server errors
+        try it again with exponential backoff, client errors are final
+        """
+        attempts = max(int(_playlist.remote_attempts), 1)
+        delay = _playlist.remote_backoff
+        error = None
+        headers = dict(headers or {})
+
+        if not any(k.lower() == 'accept-encoding' for k in headers):
+            headers['Accept-Encoding'] = ACCEPT_ENCODING
+
+        for attempt in range(1, attempts + 1):
+            if is_terminated.is_set():
+                break
+
+            try:
+                with self.session(url) as session:
+                    result = session.get(url, headers=headers,
+                                         timeout=_playlist.remote_timeout,
+                                         stream=True)
+                    size = read_limited(result)
+
+                if size is not None:
+                    error = (f'playlist is too big, {size} bytes, limit is '
+                             f'{_playlist.max_playlist_bytes} bytes')
+                    break
+
+                if result.status_code < 500:
+                    stats.add('remote_success' if result.status_code < 400
+                              else 'remote_failure')
+                    return result
+
+                error = f'status code {result.status_code}'
+            except (requests.exceptions.Timeout, socket.timeout):
+                error = ('no response in time, timeout is '
+                         f'{_playlist.remote_timeout} seconds')
+            except (requests.exceptions.ConnectionError,
+                    requests.exceptions.ChunkedEncodingError) as err:
+                # also a body which is cut off in the transfer
+                error = err
+            except requests.exceptions.ContentDecodingError as err:
+                # compressed body, which can not be read again
+                error = ('body with content encoding "'
+                         f'{result.headers.get("content-encoding")}" '
+                         f'can not be decoded: {err}')
+                break
+            except requests.exceptions.RequestException as err:
+                # redirect errors, or a wrong url, are final
+                error = err
+                break
+
+            if attempt < attempts:
+                messenger.debug(f'Playlist request failed: {error}, '
+                                f'try again in {delay:.2f} seconds')
+
+                if is_terminated.wait(delay):
+                    break
+
+                delay *= 2
+
+        if is_terminated.is_set():
+            # playout goes down, this is no server failure
+            return None
+
+        stats.add('remote_failure')
+        repeated.error(url, 'No valid playlist from url: {current_file}\n'
+                       f'Gave up after {attempt} attempts, '
+                       f'last error: {error}')
+        run_hook('remote_failed', url,
+                 f'gave up after {attempt} attempts, last error: {error}')
+
+        return None
+
+    def content_type_ok(self, url, result):
+        """
+        test content type from remote playlist, so an error page
+        from a proxy is not taken as broken playlist
+        """
+        content_type = result.headers.get('content-type') or ''
+        mime = content_type.split(';')[0].strip().lower()
+
+        if not _playlist.remote_content_types or any(
+                str(t).lower() in mime
+                for t in _playlist.remote_content_types):
+            return True
+
+        snippet = ' '.join(result.text[:200].split())
+        repeated.error(
+            url, 'Playlist from url: {current_file} has wrong content type: '
+            f'"{content_type}", content: "{snippet}"')
+        run_hook('remote_failed', url, f'content type "{content_type}"')
+
+        return False
+
+    def headers(self):
+        """
+        headers for remote playlists, with expanded environment variables
+        """
+        return {k: os.path.expandvars(str(v))
+                for k, v in _playlist.remote_headers.items()}
+
+    def load_include(self, path, parent):
+        """
+        load program from included playlist, relative paths
+        are taken from the including playlist,
+        return None when it can not be used
+        """
+        if '://' in parent:
+            path = urljoin(parent, path)
+        elif '://' not in path and not os.path.isabs(path):
+            path = os.path.normpath(
+                os.path.join(os.path.dirname(parent), path))
+
+        nodes = None
+
+        if '://' in path:
+            result = self.get_remote(path, self.headers())
+
+            if result is not None and result.ok:
+                try:
+                    content = gunzip(result.content) if is_gzip(
+                        path, result.content) else result.text
+                    nodes = parse_playlist(path, content,
+                                           result.headers.get('content-type'))
+                except ValueError as err:
+                    messenger.error(f'Include {{current_file}} is not valid\n'
+                                    f'{json_error(err)}', current_file=path)
+        elif os.path.isfile(path):
+            try:
+                nodes = self.load_file(path)
+            except OSError as err:
+                messenger.error(
+                    f'Include {{current_file}} is not readable\n{err}',
+                    current_file=path)
+        else:
+            messenger.warning('Include not exists, skip it: {current_file}',
+                              current_file=path)
+            return None, path
+
+        if not isinstance(nodes, dict) \
+                or not isinstance(nodes.get('program'), list):
+            messenger.warning(
+                'Include {current_file} has no program, skip it',
+                current_file=path)
+            return None, path
+
+        return nodes['program'], path
+
+    def include(self, nodes, json_file, depth=0):
+        """
+        put program from included playlists in place of their entry,
+        entries look like: {"include": "/playlists/blocks/morning.json"}
+        """
+        program = nodes.get('program')
+
+        if depth == 0:
+            self.included = False
+
+        if not isinstance(program, list):
+            return
+
+        expanded = []
+
+        for node in program:
+            if not isinstance(node, dict) or 'include' not in node:
+                expanded.append(node)
+                continue
+
+            self.included = True
+
+            if depth >= INCLUDE_DEPTH:
+                messenger.warning(
+                    f'Include "{node["include"]}" in {{current_file}} goes '
+                    f'deeper then {INCLUDE_DEPTH} levels, skip it',
+                    current_file=json_file)
+                continue
+
+            sub_program, path = self.load_include(str(node['include']),
+                                                  json_file)
+
+            if sub_program is not None:
+                sub_nodes = {'program': sub_program}
+                self.include(sub_nodes, path, depth + 1)
+                messenger.debug(
+                    f'Include {len(sub_nodes["program"])} clips from: '
+                    '{current_file}', current_file=path)
+                expanded.extend(sub_nodes['program'])
+
+        nodes['program'] = expanded
+
+    def resolve_path(self, path, index, json_file):
+        if '$' in path:
+            path = expand_source(path, index, json_file)
+
+        if _storage.root and path and '://' not in path \
+                and not os.path.isabs(path):
+            path = os.path.join(_storage.root, path)
+
+        return path
+
+    def resolve_source(self, node, index, json_file, key='source'):
+        """
+        expand environment variables in source, subtitle, or the list
+        of alternate sources, relative paths are taken from storage root,
+        the value from the playlist is kept for write back
+        """
+        source = node.get(key)
+
+        if isinstance(source, list):
+            node[key] = [self.resolve_path(path, index, json_file)
+                         for path in source]
+        elif isinstance(source, str):
+            node[key] = self.resolve_path(source, index, json_file)
+        else:
+            return
+
+        if node[key] != source:
+            node[f'{key}_raw'] = source
+
+    def expand_glob(self, node, index, json_file):
+        """
+        clip with wildcards in source becomes one clip for every matching
+        file, sorted by name, the files get the values from this clip,
+        missing in, out and duration are taken from the file
+        """
+        source = node.get('source')
+
+        if not isinstance(source, str) or '://' in source \
+                or not re.search(r'[*?[]', source):
+            return [node]
+
+        files = sorted(f for f in glob(source) if os.path.isfile(f))
+
+        if not files:
+            messenger.warning(
+                f'Clip {index} in {{current_file}} has no matching files, '
+                f'skip it: "{source}"', current_file=json_file)
+            return []
+
+        self.globbed = True
+        clips = []
+
+        for number, file in enumerate(files):
+            clip = deepcopy(node)
+            clip['source'] = file
+            clip.pop('source_raw', None)
+
+            # only the first file starts at the fixed begin
+            if number > 0:
+                clip.pop('begin', None)
+                clip.pop('hard_start', None)
+
+            clip.setdefault('in', 0)
+
+            if not get_float(clip.get('duration'), 0):
+                probe = MediaProbe()
+                probe.load(file)
+                clip['duration'] = get_float(
+                    probe.format.get('duration') if probe.format else None, 0)
+
+            if clip.get('out') is None:
+                clip['out'] = clip['duration']
+
+            clips.append(clip)
+
+        messenger.debug(f'Clip {index} expands to {len(files)} files: '
+                        f'"{source}"')
+
+        return clips
+
+    def shuffle_groups(self, program, json_file):
+        """
+        shuffle clips which follow each other with the same shuffle_group,
+        the order depends on the date, so it is the same for the whole day,
+        clips with fixed begin stay in place and split the group
+        """
+        self.shuffled = False
+        index = 0
+
+        while index < len(program):
+            group = program[index].get('shuffle_group')
+            end = index
+
+            while end < len(program) and group \
+                    and program[end].get('shuffle_group') == group \
+                    and 'begin_at' not in program[end] \
+                    and 'hard_start' not in program[end]:
+                end += 1
+
+            if end - index > 1:
+                block = program[index:end]
+                Random(f'{self.list_date}-{group}-{index}').shuffle(block)
+                program[index:end] = block
+                self.shuffled = True
+
+                messenger.debug(
+                    f'Shuffle {end - index} clips from group "{group}" '
+                    f'in {{current_file}}', current_file=json_file)
+
+            index = max(end, index + 1)
+
+    def fill_gap(self, program, json_file):
+        """
+        when the playlist is not long enough, put clips from fill_path
+        at the end, they are taken randomly from the clips which fit in
+        the rest time, longer clips get a higher chance, so the gap is
+        filled with less clip changes
+        """
+        self.filled = False
+
+        if not _storage.fill_path or not program or not _playlist.length \
+                or stdin_args.loop:
+            return
+
+        rest = _playlist.length - sum(
+            length for _, _, length in timeline(program))
+
+        if rest <= _playlist.length_tolerance:
+            return
+
+        pool = []
+
+        for ext in _storage.extensions:
+            for file in sorted(glob(os.path.join(
+                    _storage.fill_path, '**', f'*{ext}'), recursive=True)):
+                probe = MediaProbe()
+                probe.load(file)
+                duration = get_float(probe.format.get('duration'), 0) \
+                    if probe.format else 0
+
+                if duration > 0:
+                    pool.append((file, duration))
+
+        rand = Random(f'{self.list_date}-fill')
+        clips = []
+
+        while True:
+            fits = [clip for clip in pool if clip[1] <= rest]
+
+            if not fits:
+                break
+
+            source, duration = rand.choices(
+                fits, weights=[clip[1] for clip in fits])[0]
+            rest -= duration
+            clips.append({
+                'in': 0,
+                'out': duration,
+                'duration': duration,
+                'source': source,
+                'category': 'fill'
+            })
+
+            messenger.info(f'Fill playlist gap with "{source}", '
+                           f'{duration:.2f} seconds')
+
+        if clips:
+            program.extend(clips)
+            self.filled = True
+
+            messenger.info(
+                f'Playlist {{current_file}} is filled with {len(clips)} '
+                f'clips, {rest:.2f} seconds are still missing',
+                current_file=json_file)
+        else:
+            messenger.warning(
+                f'No clip from "{_storage.fill_path}" fits in the '
+                f'{rest:.2f} seconds, which are missing in the playlist')
+
+    def set_nodes(self, nodes, mod_time, json_file):
+        """
+        take new playlist and validate it
+        """
+        list_date = nodes.get('date')
+
+        if self.list_date is None:
+            # no date is expected, like for a diff, take the playlist date
+            self.list_date = list_date
+        elif list_date and list_date != self.list_date \
+                and json_file != '-':
+            # from stdin the date in the playlist is taken
+            messenger.warning(
+                f'Playlist date "{list_date}" in {{current_file}} does not '
+                f'match the expected date "{self.list_date}"!',
+                current_file=json_file)
+            nodes['date'] = self.list_date
+
+        program = []
+        self.globbed = False
+
+        for index, node in enumerate(nodes['program'], 1):
+            self.resolve_source(node, index, json_file)
+            self.resolve_source(node, index, json_file, 'subtitle')
+            self.resolve_source(node, index, json_file, 'sources')
+
+            for clip in self.expand_glob(node, index, json_file):
+                # clips without length would only open and close the source
+                if get_float(clip.get('out'), 0) \
+                        <= get_float(clip.get('in'), 0) \
+                        or get_float(clip.get('duration'), 0) <= 0:
+                    messenger.warning(
+                        f'Clip {index} in {{current_file}} has no length, '
+                        f'skip it: "{clip.get("source")}"',
+                        current_file=json_file)
+                    continue
+
+                # begin is computed in the player loop,
+                # so a fixed begin from the playlist gets its own key
+                if 'begin' in clip:
+                    clip['begin_at'] = clip.pop('begin')
+
+                program.append(clip)
+
+        self.shuffle_groups(program, json_file)
+        self.fill_gap(program, json_file)
+        nodes['program'] = program
+
+        self.nodes = nodes
+        self.last_mod_time = mod_time
+        self.source = json_file
+        messenger.info('Open: {current_file}', current_file=json_file)
+        run_hook('playlist_loaded', self.list_date, len(nodes['program']),
+                 json_file)
+
+        if self.validate:
+            # corrections are only written to local json files, which
+            # contain all clips, otherwise included or expanded clips get in
+            write_file = json_file if _playlist.write_back \
+                and '://' not in json_file and json_file.endswith('.json') \
+                and not self.included and not self.globbed \
+                and not self.shuffled and not self.filled else None
+
+            validate_thread(deepcopy(self.nodes), self.list_date,
+                            self.nodes['program'], write_file)
+
+    def load_file(self, json_file):
+        """
+        load local playlist, plain or gzip compressed
+        """
+        if os.path.getsize(json_file) >= STREAM_BYTES:
+            opener = gzip.open if is_gzip(json_file) else open
+
+            with opener(json_file, 'rt', encoding='utf-8-sig',
+                        errors='replace') as f:
+                head = f.read(4096)
+
+            if head.lstrip().startswith('{') \
+                    and playlist_format(json_file, head) == 'json':
+                return self.load_stream(json_file)
+
+        if is_gzip(json_file):
+            with open(json_file, 'rb') as f:
+                try:
+                    return parse_playlist(json_file, gunzip(f.read()))
+                except ValueError as err:
+                    messenger.error(
+                        'Playlist {current_file} is not valid\n'
+                        f'{json_error(err)}', current_file=json_file)
+                    return None
+
+        if not check_encoding(json_file):
+            return None
+
+        with open(json_file, 'r', encoding='utf-8-sig') as f:
+            playlist_type = playlist_format(json_file, f.read(4096))
+            f.seek(0)
+
+            if playlist_type == 'yaml':
+                try:
+                    return read_yaml(f)
+                except ValueError as err:
+                    messenger.error(
+                        'Playlist {current_file} is not YAML conform\n'
+                        f'{err}', current_file=json_file)
+                    return None
+
+            if playlist_type == 'm3u':
+                return read_m3u(f)
+
+            return valid_json(f)
+
+    def load_stream(self, json_file):
+        """
+        load big json playlist clip by clip
+        """
+        try:
+            nodes, clips = read_json_streaming(json_file)
+            nodes['program'] = list(clips)
+        except (ValueError, OSError, EOFError) as err:
+            messenger.error(
+                f'Playlist {{current_file}} is not JSON conform\n'
+                f'{json_error(err)}', current_file=json_file)
+            return None
+
+        return nodes
+
+    def get_sources(self, list_date=None):
+        """
+        playlist files from all configured paths, in failover order
+        """
+        if stdin_args.playlist:
+            return [stdin_args.playlist]
+
+        list_date = list_date or self.list_date
+        sources = []
+
+        for path in _playlist.paths:
+            json_file = playlist_file(path, list_date)
+
+            # when no json playlist exists, take other formats
+            for ext in ['.json.gz', '.yml', '.yaml']:
+                if '://' in json_file or os.path.isfile(json_file) \
+                        or not json_file.endswith('.json'):
+                    break
+
+                if os.path.isfile(json_file[:-5] + ext):
+                    json_file = json_file[:-5] + ext
+
+            sources.append(json_file)
+
+        return sources
+
+    def read(self, fallback=True):
+        """
+        try playlist sources in order, the first valid one wins,
+        when all are missing, take the fallback from missing_behavior,
+        without fallback the cached copy and missing_behavior are not used
+        """
+        self.nodes = {'program': []}
+        self.missing = True
+        last_mod_time = self.last_mod_time
+        sources = self.get_sources()
+
+        if self.read_sources(sources, last_mod_time) or not fallback:
+            return
+
+        if len(sources) > 1:
+            messenger.error('All playlist sources failed')
+
+        if _playlist.remote_cache and any('://' in s for s in sources):
+            self.read_cached(last_mod_time)
+
+        if self.error and self.missing and not stdin_args.playlist:
+            self.read_missing(last_mod_time)
+
+    def read_sources(self, sources, last_mod_time):
+        """
+        read sources in order, return True when one is valid
+        """
+        for index, json_file in enumerate(sources):
+            if is_terminated.is_set():
+                self.error = True
+                return False
+            if index > 0:
+                messenger.warning(
+                    f'Try playlist source {index + 1} of {len(sources)}: '
+                    '{current_file}', current_file=json_file)
+
+            if json_file.replace('\\', '/') != self.source:
+                # other source then the loaded one, take it in any case
+                self.last_mod_time = 0.0
+
+            self.error = False
+            self.read_source(json_file)
+
+            if not self.error:
+                return True
+
+            self.last_mod_time = last_mod_time
+
+        return False
+
+    def read_missing(self, last_mod_time):
+        """
+        playlist for this date not exists,
+        play previous day or standby playlist instead of dummy clip
+        """
+        if _playlist.missing_behavior == 'previous_day':
+            list_date = (datetime.strptime(self.list_date, '%Y-%m-%d')
+                         - timedelta(days=1)).strftime('%Y-%m-%d')
+            sources = self.get_sources(list_date)
+            info = f'play playlist from previous day {list_date}'
+        elif _playlist.missing_behavior == 'standby_file' \
+                and _playlist.standby_file:
+            sources = [_playlist.standby_file]
+            info = 'play standby playlist'
+        else:
+            return
+
+        if self.source not in [s.replace('\\', '/') for s in sources]:
+            messenger.warning(
+                f'Playlist from {self.list_date} not exists, {info}')
+
+        if not self.read_sources(sources, last_mod_time):
+            messenger.error('Fallback for missing playlist failed '
+                            f'({_playlist.missing_behavior}), play dummy clip')
+
+    def cache_file(self):
+        return os.path.join(_playlist.remote_cache, f'{self.list_date}.json')
+
+    def save_cached(self, nodes):
+        """
+        save remote playlist local, for the case the server fails later
+        """
+        cache_file = self.cache_file()
+        temp_file = cache_file + '.tmp'
+
+        try:
+            os.makedirs(_playlist.remote_cache, exist_ok=True)
+
+            with open(temp_file, 'w', encoding='utf-8') as f:
+                json.dump(nodes, f, indent=4)
+
+            os.replace(temp_file, cache_file)
+        except OSError as err:
+            messenger.warning(f'Playlist cache is not writable: {err}')
+
+    def read_cached(self, last_mod_time):
+        """
+        take the last fetched copy from remote playlist,
+        when the remote server fails
+        """
+        cache_file = self.cache_file()
+
+        if not os.path.isfile(cache_file):
+            return
+
+        mod_time = os.path.getmtime(cache_file)
+
+        if self.source == cache_file and mod_time <= last_mod_time:
+            # cached copy plays already
+            self.last_mod_time = last_mod_time
+            self.error = False
+            return
+
+        try:
+            nodes = self.load_file(cache_file)
+        except OSError as err:
+            messenger.error(
+                f'Cached playlist {{current_file}} is not readable\n{err}',
+                current_file=cache_file)
+            return
+
+        if isinstance(nodes, dict) and check_fields(nodes, cache_file):
+            fetched = time.strftime('%Y-%m-%d %H:%M:%S',
+                                    time.localtime(mod_time))
+            messenger.warning(
+                'Remote playlist failed, play cached copy from '
+                f'{fetched}, it can be outdated: {{current_file}}',
+                current_file=cache_file)
+            self.error = False
+            self.set_nodes(nodes, mod_time, cache_file)
+
+    def read_source(self, json_file):
+        if '://' in json_file:
+            json_file = json_file.replace('\\', '/')
+            headers = self.headers()
+
+            if self.cache.url == json_file:
+                if self.cache.etag:
+                    headers['If-None-Match'] = self.cache.etag
+                if self.cache.modified:
+                    headers['If-Modified-Since'] = self.cache.modified
+
+            result = self.get_remote(json_file, headers)
+
+            if result is None:
+                self.error = True
+            elif result.status_code == 304:
+                messenger.debug('Playlist not modified: {current_file}',
+                                current_file=json_file)
+
+                if self.last_mod_time < self.cache.mod_time:
+                    # playlist was dropped in between, take the cached one
+                    self.nodes = deepcopy(self.cache.nodes)
+                    self.last_mod_time = self.cache.mod_time
+                    self.source = json_file
+            elif not result.ok:
+                repeated.error(
+                    json_file, 'No valid playlist from url: {current_file}, '
+                    f'status code: {result.status_code}')
+                run_hook('remote_failed', json_file,
+                         f'status code {result.status_code}')
+                self.error = True
+
+                if result.status_code in [404, 410]:
+                    return
+            elif not self.content_type_ok(json_file, result):
+                self.error = True
+            else:
+                b_time = result.headers.get('last-modified')
+                mod_time = http_time(b_time)
+
+                if mod_time is None:
+                    if b_time:
+                        messenger.warning(
+                            f'Playlist from url: {{current_file}} has wrong '
+                            f'Last-Modified header: {b_time!r}',
+                            current_file=json_file)
+
+                    mod_time = self.clock()
+
+                if mod_time > self.last_mod_time:
+                    try:
+                        if is_gzip(json_file, result.content):
+                            content = gunzip(result.content)
+                        else:
+                            content = result.text
+
+                        nodes = parse_playlist(
+                            json_file, content,
+                            result.headers.get('content-type'))
+                    except ValueError as err:
+                        messenger.error(
+                            'Playlist from url: {current_file} '
+                            f'is not valid\n{json_error(err)}',
+                            current_file=json_file)
+                        nodes = None
+
+                    if isinstance(nodes, dict):
+                        self.include(nodes, json_file)
+
+                    if isinstance(nodes, dict) \
+                            and check_fields(nodes, json_file):
+                        if _playlist.remote_cache:
+                            self.save_cached(nodes)
+
+                        self.set_nodes(nodes, mod_time, json_file)
+                        self.cache = SimpleNamespace(
+                            url=json_file, etag=result.headers.get('etag'),
+                            modified=b_time, mod_time=mod_time,
+                            nodes=deepcopy(self.nodes))
+                    else:
+                        self.error = True
+
+        elif json_file == '-':
+            content, mod_time = read_stdin()
+
+            if mod_time > self.last_mod_time:
+                try:
+                    nodes = parse_playlist(json_file,
+                                           content.decode('utf-8-sig'))
+                except ValueError as err:
+                    messenger.error(
+                        f'Playlist from stdin is not valid\n{json_error(err)}')
+                    nodes = None
+
+                if isinstance(nodes, dict):
+                    self.include(nodes, json_file)
+
+                if isinstance(nodes, dict) \
+                        and check_fields(nodes, json_file):
+                    self.set_nodes(nodes, mod_time, json_file)
+                else:
+                    self.error = True
+        elif os.path.isfile(json_file):
+            try:
+                # check last modification time from playlist
+                mod_time = os.path.getmtime(json_file)
+
+                if mod_time > self.last_mod_time:
+                    nodes = self.load_file(json_file)
+
+                    if isinstance(nodes, dict):
+                        self.include(nodes, json_file)
+
+                    if isinstance(nodes, dict) \
+                            and check_fields(nodes, json_file):
+                        self.set_nodes(nodes, mod_time, json_file)
+                    else:
+                        self.error = True
+            except OSError as err:
+                messenger.error(
+                    f'Playlist {{current_file}} is not readable\n{err}',
+                    current_file=json_file)
+                self.error = True
+        else:
+            repeated.error(json_file, 'Playlist not exists: {current_file}')
+            self.error = True
+            return
+
+        if self.error:
+            # the playlist exists, but is not usable
+            self.missing = False
+        else:
+            repeated.reset(json_file)
+
+
+class GetSourceFromPlaylist:
+    """
+    read values from json playlist,
+    get current clip in time,
+    set ffmpeg source command
+    """
+
+    def __init__(self):
+        self.prev_date = stdin_args.date or get_date(True)
+        self.list_start = _playlist.start
+        self.utc_offset = get_utc_offset()
+        self.on_fallback = False
+        self.first = True
+        self.last = False
+        self.clip_nodes = []
+        self.node_count = 0
+        self.node = None
+        self.prev_node = None
+        self.next_node = None
+        self.current = None
+        self.inserts = []
+        self.pause_at = None
+        self.pause_nodes = None
+        self.pause_position = 0
+        self.playlist = PlaylistReader(self.prev_date, 0.0)
+        self.reloaded = None
+        self.jump = None
+        self.last_error = False
+        # wall clock time, when the current clip starts to play
+        self.started = None
+        # standby clips and position, outside broadcast hours
+        self.standby = None
+        # rpc threads change the clips, while the player walks through them,
+        # it is reentrant, so locked methods can call each other
+        self.lock = RLock()
+
+        if _watchdog.enable:
+            watchdog = Thread(name='watchdog', target=self.watchdog)
+            watchdog.daemon = True
+            watchdog.start()
+
+        if _playlist.watch_interval:
+            watcher = Thread(name='watch_playlist',
+                             target=self.watch_playlist)
+            watcher.daemon = True
+            watcher.start()
+
+    def get_playlist(self):
+        """
+        read playlist from given date and fill clip_nodes
+        when playlist is not available, reset relevant values
+        """
+        reloaded = self.reloaded
+        self.reloaded = None
+        jump = self.jump
+        self.jump = None
+
+        if jump is not None:
+            # playlist from other date, seek in the clip for the current time
+            self.playlist = jump
+            self.prev_date = jump.list_date
+            self.first = True
+            self.last = False
+        elif reloaded is not None \
+                and reloaded.list_date == self.playlist.list_date:
+            # take playlist which was reloaded in the background
+            self.playlist.nodes = reloaded.nodes
+            self.playlist.last_mod_time = reloaded.last_mod_time
+            self.playlist.source = reloaded.source
+            self.playlist.error = False
+        else:
+            self.playlist.read()
+
+        if self.last_error and not self.playlist.error and \
+                self.playlist.list_date == self.prev_date:
+            # when last playlist where not exists but now is there and
+            # is still the same playlist date,
+            # set self.first to true to seek in clip
+            # only in this situation seek in is correct!!
+            self.first = True
+            self.last_error = self.playlist.error
+
+        with self.lock:
+            if self.playlist.nodes.get('program'):
+                self.clip_nodes = self.playlist.nodes.get('program')
+                self.node_count = len(self.clip_nodes)
+
+            if self.playlist.error:
+                self.clip_nodes = []
+                self.node_count = 0
+                self.playlist.last_mod_time = 0.0
+                self.last_error = self.playlist.error
+
+        if _playlist.debug_path and self.playlist.nodes.get('program'):
+            write_debug(self.playlist)
+
+    def reload(self):
+        """
+        read current playlist again, the new clips are taken
+        at the next clip change, so the current clip plays further,
+        return how many of the upcoming clips are changed
+        """
+        reader = PlaylistReader(self.playlist.list_date, 0.0)
+        reader.read()
+
+        return self.swap_playlist(reader)
+
+    def reload_on_signal(self):
+        """
+        reload after SIGHUP, like the rpc reload,
+        the current clip plays further
+        """
+        messenger.info('Reload config file')
+        load_config()
+        messenger.info('Got SIGHUP, reload playlist')
+        changed = self.reload()
+
+        if changed is None:
+            messenger.error('Reload from playlist failed, '
+                            'current playlist stays active')
+        else:
+            messenger.info(
+                f'Reload playlist, {changed} upcoming clips changed')
+
+    def jump_to(self, list_date):
+        """
+        play playlist from given date, independent from the current date,
+        the running clip stops and the clip for the current time starts,
+        return False when the playlist not exists or is empty
+        """
+        reader = PlaylistReader(list_date, 0.0)
+        reader.read()
+
+        if reader.error or not reader.nodes.get('program'):
+            return False
+
+        self.jump = reader
+
+        if _ff.decoder and _ff.decoder.poll() is None:
+            _ff.decoder.terminate()
+
+        return True
+
+    def swap_playlist(self, reader):
+        """
+        take new playlist for the upcoming clips,
+        return how many of them are changed, or None when it is not valid
+        """
+        if reader.error or not reader.nodes.get('program'):
+            return None
+
+        played = self.node['number'] if self.node else 0
+        old_nodes = self.clip_nodes[played:]
+        new_nodes = reader.nodes['program'][played:]
+        changed = abs(len(new_nodes) - len(old_nodes))
+
+        for old_node, new_node in zip(old_nodes, new_nodes):
+            if clip_values(old_node) != clip_values(new_node):
+                changed += 1
+
+        self.reloaded = SimpleNamespace(list_date=reader.list_date,
+                                        nodes=deepcopy(reader.nodes),
+                                        last_mod_time=reader.last_mod_time,
+                                        source=reader.source)
+
+        return changed
+
+    def watch_playlist(self):
+        """
+        check playlist in background for changes,
+        the reader keeps its cache, for cheap conditional requests
+        """
+        reader = PlaylistReader(self.playlist.list_date, 0.0)
+
+        while not is_terminated.wait(_playlist.watch_interval):
+            if self.playlist.error:
+                # missing playlist is handled, and logged, by the main loop
+                continue
+
+            # compare also with a change which is not taken yet
+            pending = self.reloaded
+            last_mod_time = pending.last_mod_time if pending else \
+                self.playlist.last_mod_time
+
+            reader.list_date = self.playlist.list_date
+            reader.last_mod_time = last_mod_time
+            reader.source = pending.source if pending else \
+                self.playlist.source
+            reader.read()
+
+            if reader.last_mod_time <= last_mod_time:
+                continue
+
+            changed = self.swap_playlist(reader)
+
+            if changed is not None:
+                messenger.info(
+                    f'Playlist changed, {changed} upcoming clips changed')
+
+    def watchdog(self):
+        """
+        compare the time which the current clip plays, with its length,
+        when it plays longer then the tolerance, the player hangs,
+        with action restart the clip for the current time plays again,
+        with next the following clip starts
+        """
+        alarmed = None
+
+        while not is_terminated.wait(1):
+            node = self.current
+            started = self.started
+
+            if node is None or started is None or node is alarmed:
+                continue
+
+            length = (node['out'] - get_float(node.get('seek'), 0)) \
+                / clip_speed(node)
+            overrun = time.monotonic() - started - length
+
+            if overrun < _watchdog.tolerance:
+                continue
+
+            alarmed = node
+            messenger.error(
+                f'Playout stalled, clip plays {overrun:.2f} seconds longer '
+                f'then its length of {length:.2f} seconds, action is '
+                f'"{_watchdog.action}": "{node.get("source")}"')
+
+            if _watchdog.action not in ['restart', 'next'] \
+                    or not _ff.decoder or _ff.decoder.poll() is not None:
+                continue
+
+            if _watchdog.action == 'restart':
+                # seek to the clip for the current time, like at start
+                self.first = True
+
+            _ff.decoder.terminate()
+
+            try:
+                _ff.decoder.wait(5)
+            except TimeoutExpired:
+                messenger.error('Player not stopped, kill it')
+                _ff.decoder.kill()
+
+    def play_time(self):
+        """
+        current time in seconds, in the range from the playlist day
+        """
+        current_time = get_time('full_sec')
+
+        if _playlist.start and current_time < _playlist.start:
+            current_time += _playlist.length or 86400.0
+
+        return current_time
+
+    def elapsed(self, node):
+        """
+        play length and elapsed seconds from clip
+        """
+        current_time = self.play_time()
+
+        # seek is changed, when playout starts in the middle of the clip
+        length = (node['out'] - get_float(node.get('in'), node['seek'])) \
+            / clip_speed(node)
+
+        return length, min(max(current_time - node['begin'], 0), length)
+
+    def skip(self):
+        """
+        stop the current clip and play the next one, the current clip
+        gets cut in the playlist, so the following begin times are right,
+        return skipped and next source, or None when no playlist clip plays
+        """
+        with self.lock:
+            node = self.current
+
+            if not node or not node['number'] \
+                    or node['number'] > len(self.clip_nodes):
+                messenger.error('Skip only works while a playlist clip plays')
+                return None
+
+            _, elapsed = self.elapsed(node)
+            clip = self.clip_nodes[node['number'] - 1]
+            # a looped clip is cut also, so it plays not again
+            clip.pop('single_out', None)
+            clip['loop_count'] = 1
+            clip['out'] = get_float(clip.get('in'), 0) \
+                + elapsed * clip_speed(clip)
+
+            next_source = None
+
+            if node['number'] < len(self.clip_nodes):
+                next_source = self.clip_nodes[node['number']].get('source')
+
+        messenger.info(f'Skip clip: "{node.get("source")}"')
+
+        if _ff.decoder and _ff.decoder.poll() is None:
+            _ff.decoder.terminate()
+
+        return {'skipped': node.get('source'), 'source': next_source}
+
+    def pause(self):
+        """
+        stop the current clip and play the pause slate,
+        return paused source, or None when no playlist clip plays
+        """
+        with self.lock:
+            node = self.current
+
+            if self.pause_at is not None:
+                messenger.error('Playout is paused already')
+                return None
+
+            if not node or not node['number'] \
+                    or node['number'] > len(self.clip_nodes):
+                messenger.error(
+                    'Pause only works while a playlist clip plays')
+                return None
+
+            self.pause_at = self.play_time()
+            self.pause_nodes = self.clip_nodes
+            self.pause_position = node['number']
+
+            if _rpc.pause_shifts_schedule:
+                self.cut_for_pause(node)
+
+        messenger.info(f'Pause playout at: "{node.get("source")}"')
+
+        if _ff.decoder and _ff.decoder.poll() is None:
+            _ff.decoder.terminate()
+
+        return {'paused': node.get('source'),
+                'shift': bool(_rpc.pause_shifts_schedule)}
+
+    def cut_for_pause(self, node):
+        """
+        cut the paused clip, the rest of it plays after resume
+        """
+        with self.lock:
+            _, played = self.elapsed(node)
+            position = node['number']
+            clip = self.clip_nodes[position - 1]
+            # elapsed in clip time, with speed
+            elapsed = played * clip_speed(clip)
+            seek = get_float(clip.get('in'), 0)
+            rest = {k: v for k, v in clip.items()
+                    if k not in ['begin_at', 'hard_start', 'filter', 'probe',
+                                 'src_cmd']}
+            single = get_float(clip.get('single_out'), 0) - seek
+
+            if loop_count(clip) > 1 and single > 0:
+                # the interrupted loop plays again from its begin
+                rest['loop_count'] = loop_count(clip) - int(elapsed // single)
+            else:
+                rest['in'] = seek + elapsed
+
+            self.clip_nodes.insert(position, rest)
+            self.node_count = len(self.clip_nodes)
+
+            clip.pop('single_out', None)
+            clip['loop_count'] = 1
+            clip['out'] = seek + elapsed
+            # pause begins where the clip is cut
+            self.pause_at = node['begin'] + played
+
+    def resume(self):
+        """
+        stop the pause slate and continue with the clip for the
+        current time, when the pause shifts the schedule its length
+        gets a place in the playlist, so the following clips play later,
+        return paused seconds, or None when playout is not paused
+        """
+        with self.lock:
+            if self.pause_at is None:
+                messenger.error('Playout is not paused')
+                return None
+
+            paused = max(self.play_time() - self.pause_at, 0)
+            shift = _rpc.pause_shifts_schedule \
+                and self.clip_nodes is self.pause_nodes
+
+            if shift:
+                self.clip_nodes.insert(self.pause_position, {
+                    'in': 0,
+                    'out': paused,
+                    'duration': paused,
+                    'source': None,
+                    'category': 'pause'
+                })
+                self.node_count = len(self.clip_nodes)
+                # the rest from the paused clip begins now
+                self.last_time = self.pause_at
+            else:
+                self.first = True
+
+            self.pause_at = None
+            self.pause_nodes = None
+
+        messenger.info(f'Resume playout after {paused:.2f} seconds')
+
+        if _ff.decoder and _ff.decoder.poll() is None:
+            _ff.decoder.terminate()
+
+        return {'paused': round(paused, 3), 'shift': bool(shift)}
+
+    def status(self):
+        """
+        values from the clip which is on air, for monitoring,
+        return None when nothing is playing
+        """
+        node = self.current
+
+        if node is None:
+            return None
+
+        length, elapsed = self.elapsed(node)
+        number = node['number']
+        next_source = None
+
+        if number and number < len(self.clip_nodes):
+            next_source = self.clip_nodes[number].get('source')
+
+        return {
+            'date': self.playlist.list_date,
+            'index': number - 1 if number else None,
+            'source': node.get('source'),
+            'category': node.get('category', ''),
+            'tags': node.get('tags') or [],
+            'begin': sec_to_time(node['begin']),
+            'duration': round(length, 3),
+            'elapsed': round(elapsed, 3),
+            'remaining': round(length - elapsed, 3),
+            'next': next_source,
+            'paused': self.pause_at is not None
+        }
+
+    def upcoming(self, count):
+        """
+        next clips from the current one on, with their begin time
+        from the timeline, for rundown views,
+        the current clip is marked with its index
+        """
+        with self.lock:
+            program = list(self.clip_nodes)
+
+        node = self.current
+        current = None
+
+        if node is not None and node['number']:
+            current = first = node['number'] - 1
+        else:
+            # placeholder plays, take the clip for the current time
+            position = seek_to(program, self.play_time())
+            first = len(program) if position is None else position[0]
+
+        clips = []
+
+        for index, (clip, begin, length) in enumerate(timeline(program)):
+            if index < first:
+                continue
+
+            if len(clips) >= count:
+                break
+
+            clips.append({
+                'index': index,
+                'source': clip.get('source'),
+                'category': clip.get('category', ''),
+                'begin': sec_to_time(begin),
+                'duration': round(length, 3)
+            })
+
+        return {
+            'date': self.playlist.list_date,
+            'current': current,
+            'total': len(program),
+            'clips': clips
+        }
+
+    def insert(self, source, duration=None, mode='push', category=''):
+        """
+        queue clip for the next clip change, the following clips
+        get pushed back, with drop they are dropped, or cut at the begin,
+        with catch_up only skippable clips are dropped, to come back
+        to schedule, return clip duration, or None when clip can not
+        be inserted
+        """
+        if not self.current or not self.current['number']:
+            messenger.error('Insert only works while a playlist clip plays')
+            return None
+
+        node = self.new_clip(source, duration, category, 'Insert')
+
+        if node is None:
+            return None
+
+        with self.lock:
+            self.inserts.append((node, mode))
+
+        return node['out']
+
+    def new_clip(self, source, duration, category, action, mode=None):
+        """
+        clip node from source, without duration the file duration is taken,
+        audio only clips need the mode audio,
+        return None when clip is not playable
+        """
+        probe = MediaProbe()
+        probe.load(source)
+
+        if mode == 'audio':
+            playable_clip = bool(probe.audio)
+        else:
+            playable_clip = bool(probe.video and probe.video[0])
+
+        if not playable_clip:
+            messenger.error(f'{action} clip "{source}" is not playable')
+            return None
+
+        src_duration = get_float(probe.format.get('duration'), 0)
+        duration = get_float(duration, src_duration)
+
+        if duration <= 0:
+            messenger.error(f'{action} clip "{source}" has no duration')
+            return None
+
+        node = {
+            'in': 0,
+            'out': duration,
+            'duration': src_duration or duration,
+            'source': source,
+            'category': category
+        }
+
+        if mode is not None:
+            node['mode'] = mode
+
+        return node
 
-    # prevent looping
-    if out > node['duration']:
-        out = node['duration']
-    else:
-        messenger.warning(
-            f'Clip length is not in time, new duration is: {duration:.2f}')
+    def append(self, clips):
+        """
+        put clips at the end of the current playlist, they get begin
+        and index after the last clip, when all clips are playable,
+        return clip count and total length, or None
+        """
+        if not self.clip_nodes:
+            messenger.error('Append only works while a playlist is loaded')
+            return None
 
-    if node['duration'] > duration > 1 and \
-            node['duration'] - node['seek'] >= duration:
-        node['out'] = out
-        node = src_or_dummy(node)
-    elif node['duration'] > duration > 0.0:
-        messenger.warning(
-            f'Last clip less then 1 second long, skip:\n{node["source"]}')
-        node = None
-    else:
-        missing_secs = abs(duration - (node['duration'] - node['seek']))
-        messenger.error(
-            f'Playlist is not long enough:\n{missing_secs:.2f} seconds needed')
-        out = node['out']
-        node = src_or_dummy(node)
+        nodes = []
 
-    return node
+        for clip in clips:
+            node = self.new_clip(clip['source'], clip.get('duration'),
+                                 clip.get('category', ''), 'Append',
+                                 clip.get('mode'))
 
+            if node is None:
+                return None
 
-def timed_source(node, last):
-    """
-    prepare input clip
-    check begin and length from clip
-    return clip only if we are in 24 hours time range
-    """
-    delta, total_delta = get_delta(node['begin'])
-    node_ = None
+            nodes.append(node)
 
-    if not stdin_args.loop and _playlist.length:
-        messenger.debug(f'delta: {delta:f}')
-        messenger.debug(f'total_delta: {total_delta:f}')
-        check_sync(delta)
+        for node in nodes:
+            messenger.info(f'Append clip: "{node["source"]}"')
 
-    if (total_delta > node['out'] - node['seek'] and not last) \
-            or stdin_args.loop or not _playlist.length:
-        # when we are in the 24 houre range, get the clip
-        node_ = src_or_dummy(node)
+        with self.lock:
+            self.clip_nodes.extend(nodes)
+            self.node_count = len(self.clip_nodes)
 
-    elif total_delta <= 0:
-        messenger.info(f'Begin is over play time, skip:\n{node["source"]}')
+            _, begin, length = list(timeline(self.clip_nodes))[-1]
 
-    elif total_delta < node['duration'] - node['seek'] or last:
-        node_ = handle_list_end(total_delta, node)
+        start = _playlist.start or 0
+        total = begin + length - start
+        overrun = total - (_playlist.length or 86400.0)
 
-    return node_
+        if overrun > 0:
+            messenger.warning(
+                f'Playlist ends {overrun:.2f} seconds after the day, '
+                'through appended clips')
 
+        return {'count': self.node_count, 'duration': round(total, 3)}
 
-def check_length(total_play_time, list_date):
-    """
-    check if playlist is long enough
-    """
-    if _playlist.length and total_play_time < _playlist.length - 5 \
-            and not stdin_args.loop:
-        messenger.error(
-            f'Playlist from {list_date} is not long enough!\n'
-            f'Total play time is: {timedelta(seconds=total_play_time)}, '
-            f'target length is: {timedelta(seconds=_playlist.length)}'
-        )
+    def patch(self, operations):
+        """
+        apply patches to the loaded playlist, one after the other,
+        they replace, insert or remove a clip by its index, played clips
+        and the clip on air can not be changed, the begin times are
+        computed new from the changed clips on,
+        raise ValueError when a patch can not be applied, then the
+        playlist stays like it is
+        """
+        if not self.clip_nodes:
+            raise ValueError('Patch only works while a playlist is loaded')
 
+        clips = {}
 
-def validate_thread(clip_nodes, list_date):
-    """
-    validate json values in new thread
-    and test if source paths exist
-    """
-    def check_json(clip_nodes, list_date):
-        error = ''
-        counter = 0
-        probe = MediaProbe()
+        # probe new clips first, the player is not blocked by it
+        for number, operation in enumerate(operations, 1):
+            action = operation.get('op')
 
-        # check if all values are valid
-        for node in clip_nodes['program']:
-            source = node.get('source')
-            probe.load(source)
-            missing = []
-            _in = get_float(node.get('in'), 0)
-            _out = get_float(node.get('out'), 0)
-            duration = get_float(node.get('duration'), 0)
+            if action not in ['replace', 'insert', 'remove']:
+                raise ValueError(f'Patch {number} has unknown op: {action!r}')
 
-            if probe.is_remote:
-                if not probe.video[0]:
-                    missing.append(f'Remote file not exist: "{source}"')
-            elif source is None or not os.path.isfile(source):
-                missing.append(f'File not exist: "{source}"')
+            if action != 'remove':
+                clips[number] = self.patch_clip(operation, number)
 
-            if not type(node.get('in')) in [int, float]:
-                missing.append(f'No in Value in: "{node}"')
+        with self.lock:
+            node = self.current
 
-            if _out == 0:
-                missing.append(f'No out Value in: "{node}"')
+            if node is not None and node['number']:
+                first = node['number']
+            else:
+                # placeholder plays, the clip for the current time can change
+                position = seek_to(self.clip_nodes, self.play_time())
+                first = len(self.clip_nodes) if position is None \
+                    else position[0]
+
+            program = list(self.clip_nodes)
+
+            for number, operation in enumerate(operations, 1):
+                action = operation['op']
+                index = operation.get('index')
+                last = len(program) if action == 'insert' \
+                    else len(program) - 1
+
+                if not isinstance(index, int) or isinstance(index, bool) \
+                        or not 0 <= index <= last:
+                    raise ValueError(f'Patch {number} has no valid index: '
+                                     f'{index!r}')
+
+                if index < first:
+                    raise ValueError(f'Patch {number} changes clip {index}, '
+                                     'which is on air or played already')
+
+                if action == 'remove':
+                    del program[index]
+                elif action == 'insert':
+                    program.insert(index, clips[number])
+                else:
+                    program[index] = clips[number]
 
-            if duration == 0:
-                missing.append(f'No duration Value in: "{node}"')
+            self.clip_nodes[:] = program
+            self.node_count = len(self.clip_nodes)
 
-            counter += _out - _in
+        messenger.info(f'Apply {len(operations)} patches to playlist, '
+                       f'it has now {self.node_count} clips')
 
-            line = '\n'.join(missing)
-            if line:
-                error += line + f'\nIn line: {node}\n\n'
+        return {'applied': len(operations), 'total': self.node_count}
 
-        if error:
-            messenger.error(
-                'Validation error, check JSON playlist, '
-                f'values are missing:\n{error}'
-            )
+    def patch_clip(self, operation, number):
+        """
+        clip from patch, paths are resolved like in the playlist,
+        missing in, out and duration are taken from the file
+        """
+        clip = operation.get('clip')
 
-        check_length(counter, list_date)
+        if not isinstance(clip, dict) or not isinstance(clip.get('source'),
+                                                        str):
+            raise ValueError(f'Patch {number} has no clip with source')
 
-    if clip_nodes.get('program') and len(clip_nodes.get('program')) > 0:
-        validate = Thread(name='check_json', target=check_json,
-                          args=(clip_nodes, list_date))
-        validate.daemon = True
-        validate.start()
-    else:
-        messenger.error('Validation error: playlist are empty')
+        clip = dict(clip)
 
+        for key in ['source', 'subtitle', 'sources']:
+            self.playlist.resolve_source(clip, number, 'patch', key)
 
-class PlaylistReader:
-    def __init__(self, list_date, last_mod_time):
-        self.list_date = list_date
-        self.last_mod_time = last_mod_time
-        self.nodes = None
-        self.error = False
+        node = self.new_clip(clip['source'], clip.get('out'),
+                             clip.get('category', ''), 'Patch',
+                             clip.get('mode'))
 
-    def read(self):
-        self.nodes = {'program': []}
-        self.error = False
+        if node is None:
+            raise ValueError(
+                f'Patch {number} has no playable clip: "{clip["source"]}"')
 
-        if stdin_args.playlist:
-            json_file = stdin_args.playlist
-        else:
-            year, month, day = self.list_date.split('-')
-            json_file = os.path.join(_playlist.path, year, month,
-                                     f'{self.list_date}.json')
+        node.update(clip)
 
-        if '://' in json_file:
-            json_file = json_file.replace('\\', '/')
+        if not check_fields({'program': [node]}, 'patch', True):
+            raise ValueError(f'Patch {number} has a clip with wrong values')
 
-            try:
-                result = requests.get(json_file, timeout=1, verify=False)
-                b_time = result.headers['last-modified']
-                temp_time = time.strptime(b_time, "%a, %d %b %Y %H:%M:%S %Z")
-                mod_time = time.mktime(temp_time)
+        return node
 
-                if mod_time > self.last_mod_time:
-                    if isinstance(result.json(), dict):
-                        self.nodes = result.json()
-                    self.last_mod_time = mod_time
-                    messenger.info('Open: ' + json_file)
-                    validate_thread(deepcopy(self.nodes), self.list_date)
-            except (requests.exceptions.ConnectionError, socket.timeout):
-                messenger.error(f'No valid playlist from url: {json_file}')
-                self.error = True
+    def drop_clips(self, position, shift):
+        """
+        drop clips after insert, as long as they fit in the inserted time,
+        the rest is cut from the begin of the next clip
+        """
+        dropped = 0
+
+        while position < len(self.clip_nodes) and shift > 0:
+            node = self.clip_nodes[position]
+            seek = get_float(node.get('in'), 0)
+            length = get_float(node.get('out'), get_float(
+                node.get('duration'), 30)) - seek
+
+            if length <= shift:
+                del self.clip_nodes[position]
+                shift -= length
+                dropped += 1
+            else:
+                node['in'] = seek + shift
+                shift = 0
 
-        elif os.path.isfile(json_file):
-            # check last modification time from playlist
-            mod_time = os.path.getmtime(json_file)
-            if mod_time > self.last_mod_time:
-                with open(json_file, 'r', encoding='utf-8') as f:
-                    self.nodes = valid_json(f)
+        messenger.info(f'Drop {dropped} clips, to come back to schedule')
 
-                self.last_mod_time = mod_time
-                messenger.info('Open: ' + json_file)
-                validate_thread(deepcopy(self.nodes), self.list_date)
-        else:
-            messenger.error(f'Playlist not exists: {json_file}')
-            self.error = True
+    def catch_up(self, position, deficit):
+        """
+        drop skippable clips from position on, which fit in the deficit,
+        other clips are never dropped, at a fixed begin the schedule
+        is right again, return the seconds which are not caught up
+        """
+        dropped = 0
+
+        while position < len(self.clip_nodes) and deficit > 0:
+            node = self.clip_nodes[position]
+
+            if fixed_begin(node) is not None:
+                deficit = 0
+                break
+
+            seek = get_float(node.get('in'), 0)
+            out = get_float(node.get('out'), get_float(
+                node.get('duration'), 30))
+            length = max(out - seek, 0) * loop_count(node) \
+                / clip_speed(node)
+
+            if node.get('skippable') and length <= deficit + 0.001:
+                messenger.info(
+                    f'Drop skippable clip, to catch up: "{node["source"]}"')
+                del self.clip_nodes[position]
+                deficit -= length
+                dropped += 1
+            else:
+                position += 1
 
+        deficit = max(deficit, 0)
 
-class GetSourceFromPlaylist:
-    """
-    read values from json playlist,
-    get current clip in time,
-    set ffmpeg source command
-    """
+        if deficit:
+            messenger.warning(
+                f'Drop {dropped} skippable clips, following clips play '
+                f'{deficit:.2f} seconds later')
+        else:
+            messenger.info(
+                f'Drop {dropped} skippable clips, to come back to schedule')
 
-    def __init__(self):
-        self.prev_date = get_date(True)
-        self.list_start = _playlist.start
-        self.first = True
-        self.last = False
-        self.clip_nodes = []
-        self.node_count = 0
-        self.node = None
-        self.prev_node = None
-        self.next_node = None
-        self.playlist = PlaylistReader(get_date(True), 0.0)
-        self.last_error = False
+        return deficit
 
-    def get_playlist(self):
+    def apply_inserts(self):
         """
-        read playlist from given date and fill clip_nodes
-        when playlist is not available, reset relevant values
+        put queued clips after the current clip
         """
-        self.playlist.read()
+        inserts, self.inserts = self.inserts, []
 
-        if self.last_error and not self.playlist.error and \
-                self.playlist.list_date == self.prev_date:
-            # when last playlist where not exists but now is there and
-            # is still the same playlist date,
-            # set self.first to true to seek in clip
-            # only in this situation seek in is correct!!
-            self.first = True
-            self.last_error = self.playlist.error
+        if not self.node or not self.node['number']:
+            return
 
-        if self.playlist.nodes.get('program'):
-            self.clip_nodes = self.playlist.nodes.get('program')
-            self.node_count = len(self.clip_nodes)
+        position = self.node['number']
 
-        if self.playlist.error:
-            self.clip_nodes = []
-            self.node_count = 0
-            self.playlist.last_mod_time = 0.0
-            self.last_error = self.playlist.error
+        for node, mode in inserts:
+            messenger.info(f'Insert clip: "{node["source"]}"')
+            self.clip_nodes.insert(position, node)
+            position += 1
+
+            if mode == 'drop':
+                self.drop_clips(position, node['out'])
+            elif mode == 'catch_up':
+                self.catch_up(position, node['out'])
+
+        self.node_count = len(self.clip_nodes)
 
     def init_time(self):
         """
@@ -307,8 +2913,12 @@ class GetSourceFromPlaylist:
     def check_for_next_playlist(self, begin):
         """
         check if playlist length is 24 hours and matches current length,
-        to get the date for a new playlist
+        to get the date for a new playlist,
+        an output which is faster then realtime plays only one playlist
         """
+        if not _playout.realtime:
+            return
+
         if self.node is not None:
             out = self.node['out']
             delta = 0
@@ -321,7 +2931,8 @@ class GetSourceFromPlaylist:
                 delta, total_delta = get_delta(begin)
                 delta += seek + 1
 
-            next_start = begin - _playlist.start + out + delta
+            next_start = begin - _playlist.start \
+                + out / clip_speed(self.node) + delta
 
         else:
             delta, total_delta = get_delta(begin)
@@ -332,7 +2943,9 @@ class GetSourceFromPlaylist:
             self.playlist.list_date = self.prev_date
             self.playlist.last_mod_time = 0.0
             self.last_time = _playlist.start - 1
-            self.clip_nodes = []
+
+            with self.lock:
+                self.clip_nodes = []
 
     def previous_and_next_node(self, index):
         """
@@ -345,6 +2958,47 @@ class GetSourceFromPlaylist:
         else:
             self.next_node = None
 
+    def hard_start_sync(self):
+        """
+        clip before a hard start gets its length from the current time,
+        so the hard start is on time, also when the playout drifts
+        """
+        if not self.next_node or not _playout.realtime \
+                or self.next_node.get('hard_start') is None:
+            return
+
+        delta, _ = get_delta(self.node['begin'])
+
+        if abs(delta) < 0.04:
+            return
+
+        speed = clip_speed(self.node)
+        length = (self.node['out'] - self.node['seek']) / speed
+
+        if length + delta < 1:
+            messenger.warning(
+                f'Playout is {-delta:.2f} seconds late for hard start, '
+                f'clip plays only one second: "{self.node.get("source")}"')
+            delta = 1 - length
+
+        messenger.info(f'Change clip length by {delta:.2f} seconds, for '
+                       'hard start from the next clip')
+        self.node['out'] += delta * speed
+
+    def preroll_next(self):
+        """
+        probe next clip, some seconds before the current clip ends
+        """
+        if not _playlist.preroll or not self.node.get('number') \
+                or self.next_node is None:
+            preroll.schedule(None, 0)
+            return
+
+        remaining = (self.node['out'] - self.node['seek']) \
+            / clip_speed(self.node)
+        preroll.schedule(self.next_node.get('source'),
+                         remaining - _playlist.preroll)
+
     def generate_cmd(self):
         """
         extend clip node with ffmpeg source cmd and filters
@@ -354,11 +3008,14 @@ class GetSourceFromPlaylist:
             self.node['filter'] = build_filtergraph(self.node, self.prev_node,
                                                     self.next_node)
 
-    def generate_placeholder(self, duration):
+    def generate_placeholder(self, duration, fallback=None):
         """
         when playlist not exists, or is not long enough,
-        generate a placeholder node
+        or playout is paused, generate a placeholder node
         """
+        if not fallback and _playlist.dummy == 'file':
+            fallback = _playlist.fallback
+
         current_time = get_time('full_sec') - 86400
         # balance small difference to start time
         if _playlist.start is not None and isclose(_playlist.start,
@@ -368,14 +3025,28 @@ class GetSourceFromPlaylist:
             self.init_time()
             begin = self.last_time
 
+        source = None
+        src_duration = duration + 1
+
+        if fallback:
+            probe = MediaProbe()
+            probe.load(fallback)
+
+            if probe.format and probe.format.get('duration'):
+                source = fallback
+                src_duration = float(probe.format['duration'])
+            else:
+                messenger.warning(
+                    f'Fallback clip "{fallback}" is not playable!')
+
         self.node = {
             'begin': begin,
             'number': 0,
             'in': 0,
             'seek': 0,
             'out': duration,
-            'duration': duration + 1,
-            'source': None
+            'duration': src_duration,
+            'source': source
         }
 
         self.generate_cmd()
@@ -389,7 +3060,7 @@ class GetSourceFromPlaylist:
             # when loop paramter is set and playlist node exists,
             # jump to playlist start and play again
             self.list_start = self.node['begin'] + (
-                self.node['out'] - self.node['seek'])
+                self.node['out'] - self.node['seek']) / clip_speed(self.node)
             self.node = None
             messenger.info('Loop playlist')
 
@@ -397,11 +3068,269 @@ class GetSourceFromPlaylist:
             # playlist not exist or is corrupt/empty
             messenger.error('Clip nodes are empty!')
             self.first = False
-            self.generate_placeholder(30)
+            self.fallback_hook('playlist is missing or empty')
+            self.generate_placeholder(_playlist.dummy_len or 30)
 
         else:
             messenger.error('Playlist not long enough!')
-            self.generate_placeholder(60)
+            self.fallback_hook('playlist is not long enough')
+            self.generate_placeholder(_playlist.dummy_len or DUMMY_LEN)
+
+    def restart_clip(self, position):
+        """
+        at startup play the current clip from its in point, instead of
+        seeking in it, a gap with the offset is put before the clip,
+        so the following clips play later
+        """
+        index, offset = position
+        self.clip_nodes.insert(index, {
+            'in': 0,
+            'out': offset,
+            'duration': offset,
+            'source': None,
+            'category': 'resume'
+        })
+        self.node_count = len(self.clip_nodes)
+
+        messenger.info(f'Restart clip from its begin, following clips '
+                       f'play {offset:.2f} seconds later')
+
+        return index + 1, 0.0
+
+    def log_start(self, node):
+        """
+        log scheduled and real start time from clip, the delta
+        is the drift from the playlist timeline, positive when late,
+        with fields for the json log format
+        """
+        if not _playout.realtime:
+            return
+
+        # a clip which starts in the middle is scheduled later
+        scheduled = node['begin'] + (
+            node['seek'] - get_float(node.get('in'), node['seek'])) \
+            / clip_speed(node)
+        actual = self.play_time()
+
+        messenger.info(
+            'Clip start: index {index}, scheduled {scheduled}, '
+            'actual {actual}, delta {delta}',
+            index=node['number'] - 1 if node['number'] else '-',
+            scheduled=sec_to_time(scheduled), actual=sec_to_time(actual),
+            delta=f'{actual - scheduled:+.3f}')
+
+    def reconnect_live(self, clip):
+        """
+        live source stopped before the end of its slot, connect it
+        again for the rest of the slot, until the attempts are over,
+        skip, pause and jumps stop this
+        """
+        node = clip
+        attempt = 0
+        played = time.monotonic() - self.started
+
+        while True:
+            if played >= LIVE_STABLE:
+                attempt = 0
+
+            played = 0.0
+            length, elapsed = self.elapsed(clip)
+            rest = length - elapsed - _playlist.live_reconnect_delay
+
+            if self.current is not node or self.first \
+                    or self.pause_at is not None or is_terminated.is_set() \
+                    or rest < LIVE_MIN_REST:
+                return
+
+            if attempt >= _playlist.live_reconnect:
+                if attempt:
+                    messenger.error(
+                        f'Live source stopped after {attempt} reconnect '
+                        f'attempts, play next clip: "{clip["source"]}"')
+                return
+
+            attempt += 1
+            messenger.warning(
+                f'Live source stopped {length - elapsed:.2f} seconds before '
+                f'its end, reconnect attempt {attempt} of '
+                f'{_playlist.live_reconnect}: "{clip["source"]}"')
+
+            if is_terminated.wait(_playlist.live_reconnect_delay):
+                return
+
+            length, elapsed = self.elapsed(clip)
+            rest = length - elapsed
+            live = {k: v for k, v in clip.items()
+                    if k not in ['filter', 'probe', 'src_cmd']}
+            # in and begin stay, so status and skip see the whole slot
+            live['in'] = get_float(clip.get('in'), clip['seek'])
+            live['seek'] = clip['out'] - rest * clip_speed(clip)
+            probe = MediaProbe()
+            probe.load(clip['source'])
+
+            if not probe.video[0]:
+                messenger.error(f'Reconnect attempt {attempt} failed: '
+                                f'"{clip["source"]}"')
+                continue
+
+            live = src_or_dummy(live, probe)
+
+            messenger.info(f'Live source is connected again, plays '
+                           f'{rest:.2f} seconds: "{clip["source"]}"')
+
+            live['filter'] = build_filtergraph(
+                live, self.prev_node, self.next_node)
+            self.current = node = live
+            self.started = time.monotonic()
+            yield live
+
+            played = time.monotonic() - self.started
+
+    def standby_clips(self):
+        """
+        clips from standby playlist, or the standby clip alone,
+        empty when there is nothing to play, then the dummy plays
+        """
+        source = _playlist.broadcast_standby
+
+        if not source:
+            return []
+
+        if not source.lower().endswith(PLAYLIST_EXTENSIONS):
+            probe = MediaProbe()
+            probe.load(source)
+            duration = get_float((probe.format or {}).get('duration'), 0)
+
+            if duration <= 0:
+                messenger.error(f'Standby clip "{source}" is not playable, '
+                                'play dummy clip')
+                return []
+
+            return [{'source': source, 'in': 0, 'out': duration,
+                     'duration': duration}]
+
+        reader = PlaylistReader(None, 0.0, validate=False)
+        reader.read_source(source)
+
+        if reader.error or not (reader.nodes or {}).get('program'):
+            messenger.error('Standby playlist {current_file} is not valid, '
+                            'play dummy clip', current_file=source)
+            return []
+
+        return reader.nodes['program']
+
+    def play_standby(self, rest):
+        """
+        outside broadcast hours play the standby clips in a loop,
+        the last clip is cut, so the broadcast begins in time
+        """
+        if self.standby is None:
+            messenger.info(
+                'Outside broadcast hours, play standby until '
+                f'{sec_to_time(_playlist.broadcast_start)}')
+            self.standby = SimpleNamespace(clips=self.standby_clips(),
+                                           index=0)
+            preroll.schedule(None, 0)
+
+        if not self.standby.clips:
+            self.generate_placeholder(min(rest, DUMMY_LEN))
+            return
+
+        clip = self.standby.clips[
+            self.standby.index % len(self.standby.clips)]
+        self.standby.index += 1
+        node = {k: v for k, v in clip.items()
+                if k not in ['filter', 'probe', 'src_cmd']}
+        speed = clip_speed(node)
+        node['seek'] = get_float(node.get('in'), 0)
+        node['duration'] = get_float(node.get('duration'), 30)
+        node['out'] = min(get_float(node.get('out'), node['duration']),
+                          node['seek'] + rest * speed)
+        node['begin'] = self.play_time()
+        node['number'] = 0
+
+        self.prev_node = None
+        self.next_node = None
+        self.node = src_or_dummy(node)
+        self.node['filter'] = build_filtergraph(self.node, None, None)
+
+    def leave_standby(self):
+        """
+        broadcast hours begin, the playlist plays from the clip
+        for the current time, like at start
+        """
+        self.standby = None
+        self.prev_date = get_date(True)
+        self.playlist.list_date = self.prev_date
+        self.playlist.last_mod_time = 0.0
+        self.first = True
+        self.last = False
+
+        messenger.info(f'Broadcast hours begin, play playlist from '
+                       f'{self.prev_date}')
+
+    def fallback_hook(self, reason):
+        """
+        run hook only for the first placeholder, not for every one
+        """
+        if not self.on_fallback:
+            self.on_fallback = True
+            run_hook('fallback', self.playlist.list_date, reason)
+
+    def select_clip(self):
+        """
+        find the clip to play, and set its values from the timeline,
+        runs with the lock, return 'first' for the clip at start,
+        'next' for the following clip, or None at the playlist end,
+        with its begin and the end of the last clip
+        """
+        end = _playlist.start
+        position = None
+
+        if self.first and not _playout.realtime:
+            # output is faster then realtime, play from the first clip
+            self.init_time()
+            position = (0, 0.0)
+        elif self.first:
+            self.init_time()
+            position = seek_to(self.clip_nodes, self.last_time)
+
+            if position is not None and position[1] >= 1 \
+                    and self.started is None \
+                    and _playlist.resume == 'restart':
+                position = self.restart_clip(position)
+
+        for index, (self.node, begin, length) in enumerate(
+                timeline(self.clip_nodes)):
+            self.node['seek'] = get_float(self.node.get('in'), 0)
+            self.node['duration'] = get_float(self.node.get('duration'), 30)
+
+            if loop_count(self.node) > 1:
+                # out gets the looped length, the single one is kept
+                self.node.setdefault('single_out', get_float(
+                    self.node.get('out'), self.node['duration']))
+
+            # length includes loops, speed, and the cut for a fixed
+            # begin, out is in clip time
+            self.node['out'] = self.node['seek'] \
+                + length * clip_speed(self.node)
+            self.node['begin'] = begin
+            self.node['number'] = index + 1
+
+            # first time we end up here
+            if self.first:
+                if position is not None and position[0] == index:
+                    self.previous_and_next_node(index)
+                    return 'first', begin, end
+            elif self.last_time < begin:
+                self.last = index == self.node_count - 1
+                self.previous_and_next_node(index)
+                self.hard_start_sync()
+                return 'next', begin, end
+
+            end = begin + length
+
+        return None, None, end
 
     def next(self):
         """
@@ -409,58 +3338,321 @@ class GetSourceFromPlaylist:
         and getting the right clip node
         """
         while True:
-            self.get_playlist()
-            begin = _playlist.start
+            if _global.reload:
+                _global.reload = False
+                self.reload_on_signal()
 
-            for index, self.node in enumerate(self.clip_nodes):
-                self.node['seek'] = get_float(self.node.get('in'), 0)
-                self.node['duration'] = get_float(self.node.get('duration'),
-                                                  30)
-                self.node['out'] = get_float(self.node.get('out'),
-                                             self.node['duration'])
-                self.node['begin'] = begin
-                self.node['number'] = index + 1
-
-                # first time we end up here
-                if self.first:
-                    self.init_time()
-                    out = self.node['out']
-
-                    if self.node['duration'] > self.node['out']:
-                        out = self.node['duration']
-
-                    if self.last_time < begin + out - self.node['seek']:
-                        self.previous_and_next_node(index)
-                        self.node = handle_list_init(self.node)
-                        if self.node:
-                            self.node['filter'] = build_filtergraph(
-                                self.node, self.prev_node, self.next_node)
-                        self.first = False
-                        self.last_time = begin
-
-                        self.check_for_next_playlist(begin)
-                        break
-                elif self.last_time < begin:
-                    if index == self.node_count - 1:
-                        self.last = True
-                    else:
-                        self.last = False
+            rest = off_air() if _playout.realtime else None
 
-                    self.previous_and_next_node(index)
-                    self.generate_cmd()
-                    self.last_time = begin
+            if rest is not None:
+                self.play_standby(rest)
 
-                    self.check_for_next_playlist(begin)
-                    break
+                if self.node:
+                    self.current = self.node
+                    self.started = time.monotonic()
+                    yield self.node
+
+                continue
+
+            if self.standby is not None:
+                self.leave_standby()
+
+            self.get_playlist()
 
-                begin += self.node['out'] - self.node['seek']
+            utc_offset = get_utc_offset()
+
+            if utc_offset != self.utc_offset:
+                # seek to the clip for the new time, like at start
+                messenger.warning(
+                    'Daylight saving time change, UTC offset is now '
+                    f'{utc_offset}, continue with the clip for the new time')
+                self.utc_offset = utc_offset
+                self.first = True
+
+            if self.pause_at is not None:
+                # slate plays in parts, until playout resumes
+                self.generate_placeholder(DUMMY_LEN, _rpc.pause_slate)
+
+                if self.node:
+                    self.current = self.node
+                    self.started = time.monotonic()
+                    yield self.node
+
+                continue
+
+            # only the clip selection is locked, probing the clip and
+            # reading the next playlist runs without it
+            with self.lock:
+                if self.inserts:
+                    self.apply_inserts()
+
+                found, begin, end = self.select_clip()
+
+            if found == 'first':
+                self.node = handle_list_init(self.node) \
+                    if _playout.realtime else src_or_dummy(self.node)
+                if self.node:
+                    self.node['filter'] = build_filtergraph(
+                        self.node, self.prev_node, self.next_node)
+                self.first = False
+                self.last_time = begin
+                self.check_for_next_playlist(begin)
+            elif found == 'next':
+                self.generate_cmd()
+                self.last_time = begin
+                self.check_for_next_playlist(begin)
+            elif (not _playlist.length or not _playout.realtime) \
+                    and not stdin_args.loop:
+                # when we reach playlist end, stop script
+                messenger.info('Playlist reached end!')
+                return None
             else:
-                if not _playlist.length and not stdin_args.loop:
-                    # when we reach playlist end, stop script
-                    messenger.info('Playlist reached end!')
-                    return None
-                else:
-                    self.eof_handling(begin)
+                self.eof_handling(end)
 
             if self.node:
+                if self.node.get('number'):
+                    self.on_fallback = False
+
+                self.current = self.node
+                self.preroll_next()
+                self.log_start(self.node)
+                self.started = time.monotonic()
                 yield self.node
+
+                if is_live(self.node):
+                    yield from self.reconnect_live(self.node)
+
+
+def dry_run(list_date):
+    """
+    validate playlist and print the timeline, without playing it,
+    return exit code, 1 when the playlist has errors
+    """
+    reader = PlaylistReader(list_date, 0.0, validate=False)
+    reader.read()
+    program = reader.nodes.get('program')
+
+    if reader.error:
+        return 1
+
+    if not program:
+        messenger.error('Validation error: playlist are empty')
+        return 1
+
+    report = ValidationReport(list_date)
+    valid = check_json(deepcopy(reader.nodes), list_date, report=report)
+    total = 0
+
+    print(f'Playlist: {list_date}\n')
+    print(f'{"#":>5}  {"start":<12}  {"duration":>10}  source')
+
+    for index, (node, begin, length) in enumerate(timeline(program), 1):
+        print(f'{index:>5}  {sec_to_time(begin)}  {length:>10.2f}  '
+              f'{node.get("source")}')
+
+        total += length
+
+    print(f'\nTotal play time: {timedelta(seconds=total)}')
+    print(f'Validation: {report.count("warning")} warnings, '
+          f'{report.count("error")} errors')
+
+    return 0 if valid else 1
+
+
+def validate_range(first, last):
+    """
+    validate playlists from all days between first and last date,
+    without playing them, print a summary line for every day,
+    return exit code, 1 when playlists are missing or have errors
+    """
+    try:
+        day = datetime.strptime(first, '%Y-%m-%d')
+        end = datetime.strptime(last, '%Y-%m-%d')
+    except ValueError:
+        messenger.error(f'Dates "{first}" and "{last}" need yyyy-mm-dd')
+        return 1
+
+    if end < day:
+        messenger.error(f'Date "{last}" is before "{first}"')
+        return 1
+
+    lines = []
+    missing = invalid = 0
+
+    while day <= end:
+        list_date = day.strftime('%Y-%m-%d')
+        reader = PlaylistReader(list_date, 0.0, validate=False)
+        reader.read(fallback=False)
+        program = reader.nodes.get('program')
+        report = ValidationReport(list_date)
+
+        if reader.error and reader.missing:
+            result = 'missing'
+            missing += 1
+        elif reader.error:
+            result = 'not readable'
+            invalid += 1
+        elif not program:
+            result = 'empty'
+            invalid += 1
+        elif check_json(deepcopy(reader.nodes), list_date, report=report):
+            result = 'ok'
+        else:
+            result = 'errors'
+            invalid += 1
+
+        lines.append(f'{list_date}  {len(program or []):>5}  '
+                     f'{report.count("warning"):>8}  '
+                     f'{report.count("error"):>6}  {result}')
+        day += timedelta(days=1)
+
+    print(f'{"date":<10}  {"clips":>5}  {"warnings":>8}  {"errors":>6}  '
+          'result')
+    print('\n'.join(lines))
+    print(f'\n{len(lines)} days: {len(lines) - missing - invalid} ok, '
+          f'{missing} missing, {invalid} with errors')
+
+    return 1 if missing or invalid else 0
+
+
+def read_for_diff(json_file):
+    """
+    read playlist file or url for a diff, without validation,
+    return timeline list with node, begin and length, or None
+    """
+    reader = PlaylistReader(None, 0.0, validate=False)
+    reader.read_source(json_file)
+
+    if reader.error or reader.nodes is None:
+        messenger.error('Playlist {current_file} is not readable for diff',
+                        current_file=json_file)
+        return None
+
+    return list(timeline(reader.nodes.get('program') or []))
+
+
+def common_clips(old, new):
+    """
+    longest common sequence of sources, the clips which stay in order,
+    return index pairs from old and new
+    """
+    lengths = [[0] * (len(new) + 1) for _ in range(len(old) + 1)]
+
+    for i in range(len(old) - 1, -1, -1):
+        for j in range(len(new) - 1, -1, -1):
+            if old[i] == new[j]:
+                lengths[i][j] = lengths[i + 1][j + 1] + 1
+            else:
+                lengths[i][j] = max(lengths[i + 1][j], lengths[i][j + 1])
+
+    pairs = []
+    i = j = 0
+
+    while i < len(old) and j < len(new):
+        if old[i] == new[j]:
+            pairs.append((i, j))
+            i += 1
+            j += 1
+        elif lengths[i + 1][j] >= lengths[i][j + 1]:
+            i += 1
+        else:
+            j += 1
+
+    return pairs
+
+
+def diff_clip(index, node, begin):
+    return {
+        'index': index + 1,
+        'begin': sec_to_time(begin),
+        'in': get_float(node.get('in'), 0),
+        'out': get_float(node.get('out'), 0)
+    }
+
+
+def diff_playlists(old_file, new_file, as_json=False):
+    """
+    compare two playlists and print added, removed, moved, changed
+    and retimed clips, changed clips have other in or out values,
+    retimed clips begin at another time, because clips before changed,
+    return exit code, 0 without changes, 1 with changes, 2 on errors
+    """
+    old = read_for_diff(old_file)
+    new = read_for_diff(new_file)
+
+    if old is None or new is None:
+        return 2
+
+    changes = []
+    pairs = common_clips([n.get('source') for n, _, _ in old],
+                         [n.get('source') for n, _, _ in new])
+    removed = sorted(set(range(len(old))) - {i for i, _ in pairs})
+    added = sorted(set(range(len(new))) - {j for _, j in pairs})
+
+    for i, j in pairs:
+        if clip_values(old[i][0]) != clip_values(new[j][0]):
+            changes.append(('changed', i, j))
+        elif not isclose(old[i][1], new[j][1], abs_tol=0.001):
+            changes.append(('retimed', i, j))
+
+    for i in list(removed):
+        # same source at an other position is moved
+        for j in added:
+            if old[i][0].get('source') == new[j][0].get('source'):
+                changes.append(('moved', i, j))
+                removed.remove(i)
+                added.remove(j)
+                break
+
+    changes += [('removed', i, None) for i in removed]
+    changes += [('added', None, j) for j in added]
+    changes.sort(key=lambda c: (c[1] if c[2] is None else c[2],
+                                c[2] is not None))
+
+    result = {
+        'old': old_file,
+        'new': new_file,
+        'changes': [{
+            'change': change,
+            'source': (old[i] if j is None else new[j])[0].get('source'),
+            'old': None if i is None else diff_clip(i, *old[i][:2]),
+            'new': None if j is None else diff_clip(j, *new[j][:2])
+        } for change, i, j in changes],
+        'summary': {name: sum(1 for c in changes if c[0] == name)
+                    for name in ['added', 'removed', 'moved', 'changed',
+                                 'retimed']}
+    }
+
+    if as_json:
+        print(json.dumps(result, indent=4))
+        return 1 if changes else 0
+
+    print(f'Playlist diff: {old_file} -> {new_file}\n')
+
+    if not changes:
+        print('No changes')
+        return 0
+
+    for change in result['changes']:
+        old_clip, new_clip = change['old'], change['new']
+
+        if change['change'] == 'added':
+            info = f'#{new_clip["index"]}  {new_clip["begin"]}'
+        elif change['change'] == 'removed':
+            info = f'#{old_clip["index"]}  {old_clip["begin"]}'
+        else:
+            info = (f'#{old_clip["index"]} -> #{new_clip["index"]}  '
+                    f'{old_clip["begin"]} -> {new_clip["begin"]}')
+
+            if (old_clip['in'], old_clip['out']) \
+                    != (new_clip['in'], new_clip['out']):
+                info += (f', in {old_clip["in"]:.2f} -> '
+                         f'{new_clip["in"]:.2f}, out {old_clip["out"]:.2f}'
+                         f' -> {new_clip["out"]:.2f}')
+
+        print(f'{change["change"]:<8}  {info}  {change["source"]}')
+
+    print('\n' + ', '.join(f'{count} {name}' for name, count
+                           in result['summary'].items()))
+
+    return 1
@@ -0,0 +1,355 @@
+# -*- coding: utf-8 -*-
+
+# This file is part of ffplayout.
+#
+# ffplayout is free software: you can redistribute it and/or modify
+# it under the terms of the GNU General Public License as published by
+# the Free Software Foundation, either version 3 of the License, or
+# (at your option) any later version.
+#
+# ffplayout is distributed in the hope that it will be useful,
+# but WITHOUT ANY WARRANTY; without even the implied warranty of
+# MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+# GNU General Public License for more details.
+#
+# You should have received a copy of the GNU General Public License
+# along with ffplayout. If not, see <http://www.gnu.org/licenses/>.
+
+# ------------------------------------------------------------------------------
+
+import hmac
+import json
+from datetime import datetime
+from http.server import BaseHTTPRequestHandler, HTTPServer
+from socketserver import ThreadingMixIn
+from threading import Thread
+
+from .playlist import stats
+from .utils import _rpc, messenger
+
+# most clips which the list method returns, against huge responses
+LIST_MAX = 500
+
+# ------------------------------------------------------------------------------
+# json-rpc server, for controlling the playout
+# ------------------------------------------------------------------------------
+
+
+class RpcError(Exception):
+    """
+    error which goes back to the client, as json-rpc error object
+    """
+
+    def __init__(self, code, message):
+        super().__init__(message)
+        self.code = code
+        self.message = message
+
+
+def rpc_error(request_id, code, message):
+    return {
+        'jsonrpc': '2.0',
+        'error': {'code': code, 'message': message},
+        'id': request_id
+    }
+
+
+class PlayoutControl:
+    """
+    all rpc methods, they work on the running playlist source
+    """
+
+    def __init__(self, get_source):
+        self.get_source = get_source
+        self.methods = {
+            'append': self.append,
+            'date': self.date,
+            'insert': self.insert,
+            'list': self.list,
+            'patch': self.patch,
+            'pause': self.pause,
+            'reload': self.reload,
+            'resume': self.resume,
+            'skip': self.skip,
+            'status': self.status,
+            'validation': self.validation
+        }
+
+    def call(self, request):
+        if not isinstance(request, dict):
+            return rpc_error(None, -32600, 'Invalid Request')
+
+        request_id = request.get('id')
+        method = self.methods.get(request.get('method'))
+        params = request.get('params') or {}
+
+        if request.get('jsonrpc') != '2.0' or not isinstance(params, dict):
+            return rpc_error(request_id, -32600, 'Invalid Request')
+
+        if method is None:
+            return rpc_error(request_id, -32601, 'Method not found')
+
+        messenger.debug(f'RPC call: "{request.get("method")}"')
+
+        try:
+            result = method(params)
+        except RpcError as err:
+            return rpc_error(request_id, err.code, err.message)
+        except Exception as err:
+            messenger.error(
+                f'RPC call "{request.get("method")}" failed: {err}')
+            return rpc_error(request_id, -32603, 'Internal error')
+
+        return {'jsonrpc': '2.0', 'result': result, 'id': request_id}
+
+    def append(self, params):
+        """
+        put clips at the end of the current playlist, sources can be
+        paths or objects with source, duration and category
+        """
+        sources = params.get('sources')
+        clips = []
+
+        if not isinstance(sources, list) or not sources:
+            raise RpcError(-32602, 'Invalid params, sources needs a list')
+
+        for source in sources:
+            if isinstance(source, str):
+                source = {'source': source}
+
+            if not isinstance(source, dict) \
+                    or not isinstance(source.get('source'), str):
+                raise RpcError(-32602, f'Invalid source: {source!r}')
+
+            if source.get('mode') not in [None, 'video', 'audio', 'both']:
+                raise RpcError(-32602, f'Invalid mode: {source["mode"]!r}')
+
+            clips.append(source)
+
+        result = self.get_source.append(clips)
+
+        if result is None:
+            raise RpcError(-32000, 'Clips can not be appended')
+
+        return result
+
+    def date(self, params):
+        """
+        play playlist from given date, independent from the current date
+        """
+        list_date = params.get('date')
+
+        try:
+            datetime.strptime(list_date, '%Y-%m-%d')
+        except (TypeError, ValueError):
+            raise RpcError(-32602, 'Invalid params, date needs yyyy-mm-dd')
+
+        if not self.get_source.jump_to(list_date):
+            raise RpcError(-32000, f'No valid playlist from {list_date}')
+
+        messenger.info(f'Jump to playlist from {list_date}')
+
+        return {'date': list_date}
+
+    def insert(self, params):
+        """
+        play clip at next clip change, mode 'push' moves the following
+        clips back, mode 'drop' drops clips to come back to schedule,
+        mode 'catch_up' drops only skippable clips
+        """
+        source = params.get('source')
+        mode = params.get('mode', 'push')
+
+        if not isinstance(source, str) \
+                or mode not in ['push', 'drop', 'catch_up']:
+            raise RpcError(-32602, 'Invalid params')
+
+        duration = self.get_source.insert(
+            source, params.get('duration'), mode,
+            params.get('category', ''))
+
+        if duration is None:
+            raise RpcError(-32000, 'Clip can not be inserted')
+
+        return {'source': source, 'duration': duration, 'mode': mode}
+
+    def list(self, params):
+        """
+        upcoming clips with index, source, duration and begin time,
+        from the clip which is on air on, count is capped by LIST_MAX
+        """
+        count = params.get('count', 10)
+
+        if not isinstance(count, int) or isinstance(count, bool) \
+                or count < 1:
+            raise RpcError(-32602, 'Invalid params, count needs a number')
+
+        return self.get_source.upcoming(min(count, LIST_MAX))
+
+    def patch(self, params):
+        """
+        change upcoming clips with a list of patch operations, which
+        replace, insert or remove a clip by its index
+        """
+        operations = params.get('operations')
+
+        if not isinstance(operations, list) or not operations \
+                or not all(isinstance(o, dict) for o in operations):
+            raise RpcError(-32602,
+                           'Invalid params, operations needs a list')
+
+        try:
+            return self.get_source.patch(operations)
+        except ValueError as err:
+            raise RpcError(-32000, str(err))
+
+    def pause(self, params):
+        """
+        play pause slate, until playout resumes
+        """
+        result = self.get_source.pause()
+
+        if result is None:
+            raise RpcError(-32000, 'Playout can not be paused')
+
+        return result
+
+    def reload(self, params):
+        """
+        read current playlist again, upcoming clips get replaced
+        """
+        changed = self.get_source.reload()
+
+        if changed is None:
+            raise RpcError(
+                -32000, 'Playlist is not valid, current playlist stays active')
+
+        messenger.info(f'Reload playlist, {changed} upcoming clips changed')
+
+        return {'changed': changed}
+
+    def resume(self, params):
+        """
+        continue playout after pause
+        """
+        result = self.get_source.resume()
+
+        if result is None:
+            raise RpcError(-32000, 'Playout is not paused')
+
+        return result
+
+    def skip(self, params):
+        """
+        stop the current clip, the next clip starts directly
+        """
+        result = self.get_source.skip()
+
+        if result is None:
+            raise RpcError(-32000, 'No playlist clip is playing')
+
+        return result
+
+    def status(self, params):
+        """
+        current clip with elapsed and remaining time, and the next clip
+        """
+        status = self.get_source.status()
+
+        if status is None:
+            raise RpcError(-32000, 'No clip is playing')
+
+        return status
+
+    def validation(self, params):
+        """
+        report from the last playlist validation
+        """
+        if stats.report is None:
+            raise RpcError(-32000, 'No playlist is validated')
+
+        return stats.report.to_dict()
+
+
+class RpcHandler(BaseHTTPRequestHandler):
+    """
+    read json-rpc requests from http post body
+    """
+
+    def log_message(self, format, *args):
+        messenger.debug(f'RPC {self.address_string()} {format % args}')
+
+    def send_json(self, response, status=200):
+        body = json.dumps(response).encode('utf-8')
+
+        self.send_response(status)
+        self.send_header('Content-Type', 'application/json')
+        self.send_header('Content-Length', str(len(body)))
+        self.end_headers()
+        self.wfile.write(body)
+
+    def authorized(self, request):
+        """
+        compare bearer token from Authorization header in constant time,
+        public methods work without token
+        """
+        if not _rpc.token:
+            return True
+
+        if isinstance(request, dict) \
+                and request.get('method') in _rpc.public_methods:
+            return True
+
+        header = self.headers.get('Authorization') or ''
+
+        return hmac.compare_digest(header.encode('utf-8'),
+                                   f'Bearer {_rpc.token}'.encode('utf-8'))
+
+    def do_POST(self):
+        length = int(self.headers.get('Content-Length') or 0)
+
+        try:
+            request = json.loads(self.rfile.read(length))
+        except ValueError:
+            self.send_json(rpc_error(None, -32700, 'Parse error'))
+            return
+
+        if not self.authorized(request):
+            messenger.warning('RPC request without valid token from '
+                              f'{self.address_string()}')
+            self.send_json(rpc_error(
+                request.get('id') if isinstance(request, dict) else None,
+                -32001, 'Unauthorized'), 401)
+            return
+
+        self.send_json(self.server.control.call(request))
+
+
+class RpcServer(ThreadingMixIn, HTTPServer):
+    daemon_threads = True
+
+
+def rpc_server(get_source):
+    """
+    start rpc server in background thread, when it is enabled
+    """
+    if not _rpc.enable:
+        return None
+
+    host, port = _rpc.address.rsplit(':', 1)
+
+    try:
+        server = RpcServer((host, int(port)), RpcHandler)
+    except (OSError, ValueError) as err:
+        messenger.error(f'RPC server could not start on {_rpc.address}\n{err}')
+        return None
+
+    server.control = PlayoutControl(get_source)
+
+    thread = Thread(name='rpc_server', target=server.serve_forever)
+    thread.daemon = True
+    thread.start()
+
+    messenger.info(f'RPC server listening on: {_rpc.address}')
+
+    return server
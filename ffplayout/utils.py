@@ -17,11 +17,13 @@
 
 # ------------------------------------------------------------------------------
 
+import gzip
 import json
 import logging
 import math
 import os
 import re
+import shlex
 import signal
 import smtplib
 import socket
@@ -34,13 +36,24 @@ from email.mime.multipart import MIMEMultipart
 from email.mime.text import MIMEText
 from email.utils import formatdate
 from glob import glob
+from io import BytesIO
 from logging.handlers import TimedRotatingFileHandler
 from shutil import which
-from subprocess import STDOUT, CalledProcessError, check_output
+from subprocess import (DEVNULL, STDOUT, CalledProcessError, Popen,
+                        check_output)
+from threading import Event, Lock, Thread, Timer
 from types import SimpleNamespace
 
 import yaml
 
+try:
+    from zoneinfo import ZoneInfo, ZoneInfoNotFoundError
+except ImportError:
+    try:
+        from backports.zoneinfo import ZoneInfo, ZoneInfoNotFoundError
+    except ImportError:
+        ZoneInfo = None
+
 # path to user define configs
 CONFIG_PATH = os.path.join(os.path.dirname(os.path.abspath(__file__)),
                            'conf.d')
@@ -68,11 +81,11 @@ stdin_parser.add_argument(
 )
 
 stdin_parser.add_argument(
-    '-m', '--mode', help='set output mode: desktop, hls, stream'
+    '-m', '--mode', help='set output mode: desktop, hls, srt, stream'
 )
 
 stdin_parser.add_argument(
-    '-p', '--playlist', help='path from playlist'
+    '-p', '--playlist', help='path from playlist, "-" reads it from stdin'
 )
 
 stdin_parser.add_argument(
@@ -85,6 +98,39 @@ stdin_parser.add_argument(
     help='set length in "hh:mm:ss", "none" for no length check'
 )
 
+stdin_parser.add_argument(
+    '--date', help='play playlist from date in "yyyy-mm-dd", '
+    'independent from the current date'
+)
+
+stdin_parser.add_argument(
+    '--generate-playlist', nargs='?', const='', metavar='DATE',
+    help='generate playlist for "yyyy-mm-dd" from storage folder and exit, '
+    'without date for the current day'
+)
+
+stdin_parser.add_argument(
+    '--dry-run', nargs='?', const='', metavar='DATE',
+    help='print timeline from playlist in "yyyy-mm-dd" and exit, '
+    'without date take the current playlist'
+)
+
+stdin_parser.add_argument(
+    '--validate-range', nargs=2, metavar=('FROM', 'TO'),
+    help='validate playlists from all days between "yyyy-mm-dd" FROM and '
+    'TO and exit'
+)
+
+stdin_parser.add_argument(
+    '--diff', nargs=2, metavar=('OLD', 'NEW'),
+    help='print added, removed, moved, changed and retimed clips between '
+    'playlist files OLD and NEW and exit'
+)
+
+stdin_parser.add_argument(
+    '--json', help='print --diff result as JSON', action='store_true'
+)
+
 # read dynamical new arguments
 for arg_file in glob(os.path.join(CONFIG_PATH, 'argparse_*')):
     with open(arg_file, 'r') as _file:
@@ -105,6 +151,17 @@ stdin_args = stdin_parser.parse_args()
 # clock
 # ------------------------------------------------------------------------------
 
+def get_now():
+    """
+    get current date and time,
+    in the channel time zone, when one is configured
+    """
+    if _general.timezone:
+        return datetime.now(_general.timezone).replace(tzinfo=None)
+
+    return datetime.now()
+
+
 def get_time(time_format):
     """
     get different time formats:
@@ -112,7 +169,7 @@ def get_time(time_format):
         - stamp > current date time in seconds
         - else > current time in HH:MM:SS
     """
-    t = datetime.today()
+    t = get_now()
 
     if time_format == 'full_sec':
         return t.hour * 3600 + t.minute * 60 + t.second \
@@ -127,7 +184,7 @@ def get_time(time_format):
 # default variables and values
 # ------------------------------------------------------------------------------
 
-_general = SimpleNamespace()
+_general = SimpleNamespace(timezone=None)
 _mail = SimpleNamespace()
 _log = SimpleNamespace()
 _pre = SimpleNamespace()
@@ -135,10 +192,27 @@ _playlist = SimpleNamespace()
 _storage = SimpleNamespace()
 _text = SimpleNamespace()
 _playout = SimpleNamespace()
+_rpc = SimpleNamespace()
+_metrics = SimpleNamespace()
+_hooks = SimpleNamespace()
+_watchdog = SimpleNamespace()
 
 _init = SimpleNamespace(load=True)
 _ff = SimpleNamespace(decoder=None, encoder=None)
-_global = SimpleNamespace(time_delta=0)
+# reload is set by SIGHUP, the player reads the playlist again
+_global = SimpleNamespace(time_delta=0, reload=False)
+
+# is set when playout is going down, long running loops should check it
+is_terminated = Event()
+
+# length for clips without duration
+DUMMY_LEN = 60.0
+
+# limit for decompressed playlists, in bytes
+MAX_GZIP_SIZE = 100 * 1024 * 1024
+
+# url schemes from live streams, they get connected again, when they stop
+LIVE_SCHEMES = ['rtmp', 'rtmps', 'rtsp', 'srt', 'udp', 'rtp']
 
 
 def str_to_sec(s):
@@ -153,6 +227,89 @@ def str_to_sec(s):
             sys.exit(1)
 
 
+def daypart_time(value):
+    """
+    daypart begin or end in seconds, unquoted times in yaml
+    are read as number already
+    """
+    if isinstance(value, (int, float)):
+        return float(value)
+
+    return str_to_sec(str(value)) or 0.0
+
+
+def daypart(begin=None):
+    """
+    daypart with encoder params for the clip begin in seconds,
+    or for the current time, a daypart can go over midnight,
+    without fitting daypart the default ffmpeg_param are taken
+    """
+    if begin is None:
+        begin = get_time('full_sec')
+
+    begin %= 86400
+
+    for part in _playout.dayparts:
+        if part.start <= part.end:
+            fits = part.start <= begin < part.end
+        else:
+            fits = begin >= part.start or begin < part.end
+
+        if fits:
+            return part
+
+    return SimpleNamespace(name='default',
+                           ffmpeg_param=_playout.ffmpeg_param)
+
+
+def off_air():
+    """
+    seconds until the broadcast hours begin, None in broadcast hours,
+    or when they are not set, the hours can go over midnight
+    """
+    start = _playlist.broadcast_start
+    end = _playlist.broadcast_end
+
+    if start is None or end is None or start == end:
+        return None
+
+    now = get_time('full_sec') % 86400
+
+    if start < end:
+        on_air = start <= now < end
+    else:
+        on_air = now >= start or now < end
+
+    return None if on_air else (start - now) % 86400
+
+
+def sec_to_time(sec):
+    """
+    format seconds to time of day in HH:MM:SS.mmm
+    """
+    sec = round(sec % 86400, 3)
+    return (f'{int(sec // 3600):02d}:{int(sec % 3600 // 60):02d}:'
+            f'{sec % 60:06.3f}')
+
+
+def get_timezone(name):
+    """
+    get time zone object from IANA name, like Europe/Berlin
+    """
+    if not name:
+        return None
+
+    if ZoneInfo is None:
+        print('Time zone support needs python 3.9+, or backports.zoneinfo!')
+        sys.exit(1)
+
+    try:
+        return ZoneInfo(name)
+    except (ZoneInfoNotFoundError, ValueError):
+        print(f'Unknown time zone: {name}')
+        sys.exit(1)
+
+
 def read_config(path):
     with open(path, 'r') as config_file:
         return yaml.safe_load(config_file)
@@ -187,6 +344,22 @@ def load_config():
 
     _general.stop = cfg['general']['stop_on_error']
     _general.threshold = cfg['general']['stop_threshold']
+    _general.timezone = get_timezone(cfg['general'].get('timezone'))
+    _general.probe_cache = cfg['general'].get('probe_cache', False)
+    _general.probe_cache_path = cfg['general'].get(
+        'probe_cache_path',
+        os.path.join(tempfile.gettempdir(), 'ffplayout', 'probe_cache.json'))
+    _general.proxy = cfg['general'].get('proxy')
+    _general.no_proxy = cfg['general'].get('no_proxy') or []
+
+    # proxy goes over environment, then it works for requests and for ffmpeg
+    if _general.proxy:
+        for name in ['http_proxy', 'https_proxy', 'HTTP_PROXY', 'HTTPS_PROXY']:
+            os.environ[name] = _general.proxy
+
+    if _general.no_proxy:
+        os.environ['no_proxy'] = os.environ['NO_PROXY'] = ','.join(
+            _general.no_proxy)
 
     _mail.subject = cfg['mail']['subject']
     _mail.server = cfg['mail']['smpt_server']
@@ -206,16 +379,81 @@ def load_config():
     _pre.loud_tp = cfg['processing']['loud_TP']
     _pre.loud_lra = cfg['processing']['loud_LRA']
     _pre.output_count = cfg['processing']['output_count']
+    _pre.vfr_check = cfg['processing'].get('vfr_check', False)
+    _pre.vfr_fix = cfg['processing'].get('vfr_fix', False)
+    _pre.audio_languages = cfg['processing'].get('audio_languages') or []
+    _pre.audio_background = cfg['processing'].get('audio_background')
 
     _playlist.mode = cfg['playlist']['playlist_mode']
-    _playlist.path = cfg['playlist']['path']
+    # path can be a list, to try the paths one after the other
+    paths = cfg['playlist']['path']
+    _playlist.paths = paths if isinstance(paths, list) else [paths]
+    _playlist.path = _playlist.paths[0]
     _playlist.start = p_start
     _playlist.length = p_length
+    _playlist.dummy_len = cfg['playlist'].get('dummy_len')
+    _playlist.fallback = cfg['playlist'].get('fallback')
+    _playlist.dummy = cfg['playlist'].get('dummy') or (
+        'file' if _playlist.fallback else 'black')
+    _playlist.dummy_pattern = cfg['playlist'].get(
+        'dummy_pattern') or 'smptebars'
+    _playlist.resume = cfg['playlist'].get('resume') or 'seek'
+    _playlist.max_duration = cfg['playlist'].get('max_duration')
+    _playlist.live_reconnect = cfg['playlist'].get('live_reconnect', 3) or 0
+    _playlist.live_reconnect_delay = cfg['playlist'].get(
+        'live_reconnect_delay', 2) or 0
+    _playlist.default_format = cfg['playlist'].get(
+        'default_format') or 'json'
+    _playlist.filename = cfg['playlist'].get(
+        'filename') or '%Y/%m/%Y-%m-%d.json'
+    _playlist.length_tolerance = cfg['playlist'].get('length_tolerance', 5)
+    _playlist.remote_headers = cfg['playlist'].get('remote_headers') or {}
+    _playlist.remote_timeout = cfg['playlist'].get('remote_timeout', 1)
+    _playlist.remote_attempts = cfg['playlist'].get('remote_attempts', 4)
+    _playlist.remote_backoff = cfg['playlist'].get('remote_backoff', 0.2)
+    _playlist.watch_interval = cfg['playlist'].get('watch_interval')
+    _playlist.check_remote = cfg['playlist'].get('check_remote', False)
+    _playlist.check_duplicates = cfg['playlist'].get('check_duplicates')
+    _playlist.duration_tolerance = cfg['playlist'].get(
+        'duration_tolerance', 1)
+    _playlist.clamp_out = cfg['playlist'].get('clamp_out', False)
+    _playlist.debug_path = cfg['playlist'].get('debug_path')
+    _playlist.write_back = cfg['playlist'].get('write_back', False)
+    _playlist.preroll = cfg['playlist'].get('preroll', 2)
+    _playlist.remote_cache = cfg['playlist'].get('remote_cache')
+    _playlist.allow_input_args = cfg['playlist'].get(
+        'allow_input_args', False)
+    _playlist.remote_content_types = cfg['playlist'].get(
+        'remote_content_types')
+    _playlist.missing_behavior = cfg['playlist'].get(
+        'missing_behavior') or 'dummy'
+    _playlist.standby_file = cfg['playlist'].get('standby_file')
+    _playlist.broadcast_start = None
+    _playlist.broadcast_end = None
+
+    if cfg['playlist'].get('broadcast_start') is not None \
+            and cfg['playlist'].get('broadcast_end') is not None:
+        _playlist.broadcast_start = daypart_time(
+            cfg['playlist']['broadcast_start']) % 86400
+        _playlist.broadcast_end = daypart_time(
+            cfg['playlist']['broadcast_end']) % 86400
+
+    _playlist.broadcast_standby = cfg['playlist'].get(
+        'broadcast_standby') or _playlist.standby_file
+    _playlist.max_playlist_bytes = cfg['playlist'].get(
+        'max_playlist_bytes') or 20971520
+    _playlist.remote_redirects = cfg['playlist'].get('remote_redirects', 30)
+    _playlist.remote_same_host = cfg['playlist'].get(
+        'remote_same_host', False)
+    _playlist.accept_invalid_certs = cfg['playlist'].get(
+        'accept_invalid_certs', False)
 
     _storage.path = cfg['storage']['path']
     _storage.filler = cfg['storage']['filler_clip']
     _storage.extensions = cfg['storage']['extensions']
     _storage.shuffle = cfg['storage']['shuffle']
+    _storage.root = cfg['storage'].get('root')
+    _storage.fill_path = cfg['storage'].get('fill_path')
 
     _text.add_text = cfg['text']['add_text']
     _text.over_pre = cfg['text']['over_pre']
@@ -224,6 +462,22 @@ def load_config():
     _text.text_from_filename = cfg['text']['text_from_filename']
     _text.style = cfg['text']['style']
     _text.regex = cfg['text']['regex']
+    _text.up_next = cfg['text'].get('up_next', False)
+    _text.up_next_lead = float(cfg['text'].get('up_next_lead') or 15)
+    _text.up_next_duration = float(
+        cfg['text'].get('up_next_duration') or 10)
+    _text.up_next_label = str(
+        cfg['text'].get('up_next_label', 'Up next: ') or '')
+
+    hooks_cfg = cfg.get('hooks') or {}
+    _hooks.playlist_loaded = hooks_cfg.get('playlist_loaded')
+    _hooks.remote_failed = hooks_cfg.get('remote_failed')
+    _hooks.fallback = hooks_cfg.get('fallback')
+
+    watchdog_cfg = cfg.get('watchdog') or {}
+    _watchdog.enable = watchdog_cfg.get('enable', False)
+    _watchdog.tolerance = watchdog_cfg.get('tolerance', 10)
+    _watchdog.action = watchdog_cfg.get('action') or 'log'
 
     if _init.load:
         _log.to_file = cfg['logging']['log_to_file']
@@ -231,6 +485,7 @@ def load_config():
         _log.path = cfg['logging']['log_path']
         _log.level = cfg['logging']['log_level']
         _log.ff_level = cfg['logging']['ffmpeg_level']
+        _log.format = cfg['logging'].get('log_format') or 'text'
 
         _pre.w = cfg['processing']['width']
         _pre.h = cfg['processing']['height']
@@ -247,6 +502,34 @@ def load_config():
         _playout.ffmpeg_param = cfg['out']['ffmpeg_param'].split(' ')
         _playout.stream_output = cfg['out']['stream_output'].split(' ')
         _playout.hls_output = cfg['out']['hls_output'].split(' ')
+        _playout.realtime = cfg['out'].get('realtime', True)
+        _playout.srt_output = cfg['out'].get('srt_output') or ''
+        _playout.srt_mode = cfg['out'].get('srt_mode') or 'caller'
+        _playout.srt_latency = cfg['out'].get('srt_latency')
+        _playout.srt_passphrase = cfg['out'].get('srt_passphrase')
+        _playout.language_outputs = [
+            o.split(' ') for o in cfg['out'].get('language_outputs') or []]
+        _playout.dayparts = [
+            SimpleNamespace(
+                name=part.get('name') or f'{part["start"]}-{part["end"]}',
+                start=daypart_time(part['start']),
+                end=daypart_time(part['end']),
+                ffmpeg_param=part['ffmpeg_param'].split(' '))
+            for part in cfg['out'].get('dayparts') or []]
+
+        rpc_cfg = cfg.get('rpc_server') or {}
+        _rpc.enable = rpc_cfg.get('enable', False)
+        _rpc.address = rpc_cfg.get('address', '127.0.0.1:7070')
+        _rpc.pause_slate = rpc_cfg.get('pause_slate')
+        _rpc.pause_shifts_schedule = rpc_cfg.get(
+            'pause_shifts_schedule', False)
+        # a token from environment keeps it out of the config file
+        _rpc.token = os.path.expandvars(str(rpc_cfg.get('token') or ''))
+        _rpc.public_methods = rpc_cfg.get('public_methods') or []
+
+        metrics_cfg = cfg.get('metrics') or {}
+        _metrics.enable = metrics_cfg.get('enable', False)
+        _metrics.address = metrics_cfg.get('address', '127.0.0.1:9110')
 
         _init.load = False
 
@@ -308,6 +591,47 @@ class CustomFormatter(logging.Formatter):
         return formatter.format(record)
 
 
+class PlainFormatter(logging.Formatter):
+    """
+    Logging Formatter for files, ANSI color codes, which can come
+    for example from ffmpeg, are removed
+    """
+
+    ansi = re.compile(r'\x1b\[[\d;]*m')
+
+    def format(self, record):
+        return self.ansi.sub('', super().format(record))
+
+
+class JsonFormatter(logging.Formatter):
+    """
+    Logging Formatter for one json object per line, for log collectors,
+    values from messenger fields are not in the message, they get own keys
+    """
+
+    ansi = PlainFormatter.ansi
+    placeholder = re.compile(r':?\s*"?\{\w+\}"?')
+
+    def format(self, record):
+        fields = getattr(record, 'fields', None) or {}
+        message = record.getMessage()
+
+        if fields:
+            message = self.placeholder.sub(
+                '', record.template.replace('\n', ' ')).strip(' :,')
+
+        entry = {
+            'timestamp': datetime.fromtimestamp(record.created).astimezone(
+                ).isoformat(timespec='milliseconds'),
+            'level': record.levelname,
+            'module': record.name,
+            'message': self.ansi.sub('', message)
+        }
+        entry.update({k: str(v) for k, v in fields.items()})
+
+        return json.dumps(entry)
+
+
 # If the log file is specified on the command line then override the default
 if stdin_args.log:
     _log.path = stdin_args.log
@@ -319,7 +643,9 @@ decoder_logger.setLevel(_log.ff_level)
 encoder_logger = logging.getLogger('encoder')
 encoder_logger.setLevel(_log.ff_level)
 
-if _log.to_file and _log.path != 'none':
+if _log.to_file and _log.path != 'none' and stdin_args.dry_run is None \
+        and stdin_args.generate_playlist is None \
+        and stdin_args.validate_range is None:
     if _log.path and os.path.isdir(_log.path):
         playout_log = os.path.join(_log.path, 'ffplayout.log')
         decoder_log = os.path.join(_log.path, 'decoder.log')
@@ -332,8 +658,12 @@ if _log.to_file and _log.path != 'none':
         decoder_log = os.path.join(log_dir, 'decoder.log')
         encoder_log = os.path.join(log_dir, 'encoder.log')
 
-    p_format = logging.Formatter('[%(asctime)s] [%(levelname)s]  %(message)s')
-    f_format = logging.Formatter('[%(asctime)s]  %(message)s')
+    if _log.format == 'json':
+        p_format = f_format = JsonFormatter()
+    else:
+        p_format = PlainFormatter(
+            '[%(asctime)s] [%(levelname)s]  %(message)s')
+        f_format = PlainFormatter('[%(asctime)s]  %(message)s')
     p_file_handler = TimedRotatingFileHandler(playout_log, when='midnight',
                                               backupCount=_log.backup_count)
     d_file_handler = TimedRotatingFileHandler(decoder_log, when='midnight',
@@ -352,13 +682,24 @@ if _log.to_file and _log.path != 'none':
     ENC_PREFIX = ''
 else:
     console_handler = logging.StreamHandler()
-    console_handler.setFormatter(CustomFormatter())
     playout_logger.addHandler(console_handler)
     decoder_logger.addHandler(console_handler)
     encoder_logger.addHandler(console_handler)
 
-    DEC_PREFIX = '[decoder] '
-    ENC_PREFIX = '[encoder] '
+    if _log.format == 'json':
+        console_handler.setFormatter(JsonFormatter())
+        DEC_PREFIX = ''
+        ENC_PREFIX = ''
+    else:
+        if console_handler.stream.isatty():
+            console_handler.setFormatter(CustomFormatter())
+        else:
+            # console output is redirected to a file, or journal
+            console_handler.setFormatter(PlainFormatter(
+                '[%(asctime)s] [%(levelname)s]  %(message)s'))
+
+        DEC_PREFIX = '[decoder] '
+        ENC_PREFIX = '[encoder] '
 
 
 # ------------------------------------------------------------------------------
@@ -451,20 +792,34 @@ class Messenger:
     def __init__(self):
         self._mailer = Mailer()
 
-    def debug(self, msg):
-        playout_logger.debug(msg.replace('\n', ' '))
+    def render(self, msg, fields):
+        """
+        fill {name} placeholders in message with field values,
+        log formatter can take the fields also as own values
+        """
+        for key, value in fields.items():
+            msg = msg.replace(f'{{{key}}}', str(value))
 
-    def info(self, msg):
-        playout_logger.info(msg.replace('\n', ' '))
-        self._mailer.info(msg)
+        return msg
 
-    def warning(self, msg):
-        playout_logger.warning(msg.replace('\n', ' '))
-        self._mailer.warning(msg)
+    def log(self, level, msg, fields):
+        playout_logger.log(level, self.render(msg, fields).replace('\n', ' '),
+                           extra={'template': msg, 'fields': fields})
 
-    def error(self, msg):
-        playout_logger.error(msg.replace('\n', ' '))
-        self._mailer.error(msg)
+    def debug(self, msg, **fields):
+        self.log(logging.DEBUG, msg, fields)
+
+    def info(self, msg, **fields):
+        self.log(logging.INFO, msg, fields)
+        self._mailer.info(self.render(msg, fields))
+
+    def warning(self, msg, **fields):
+        self.log(logging.WARNING, msg, fields)
+        self._mailer.warning(self.render(msg, fields))
+
+    def error(self, msg, **fields):
+        self.log(logging.ERROR, msg, fields)
+        self._mailer.error(self.render(msg, fields))
 
 
 messenger = Messenger()
@@ -537,6 +892,145 @@ def validate_ffmpeg_libs():
 # probe media infos
 # ------------------------------------------------------------------------------
 
+class ProbeCache:
+    """
+    store ffprobe results on disk, a file is only probed again,
+    when its modification time or size has changed
+    """
+
+    def __init__(self):
+        self.lock = Lock()
+        self.entries = None
+        self.changed = False
+
+    def load(self):
+        if self.entries is not None:
+            return
+
+        self.entries = {}
+
+        if os.path.isfile(_general.probe_cache_path):
+            try:
+                with open(_general.probe_cache_path, 'r') as cache_file:
+                    self.entries = json.load(cache_file)
+            except (OSError, ValueError) as err:
+                messenger.warning(f'Probe cache is not readable: {err}')
+
+    def get(self, path):
+        with self.lock:
+            self.load()
+            entry = self.entries.get(path)
+
+        if entry and entry['mod_time'] == os.path.getmtime(path) \
+                and entry['size'] == os.path.getsize(path):
+            return entry['info']
+
+        return None
+
+    def set(self, path, info):
+        with self.lock:
+            self.load()
+            self.entries[path] = {
+                'mod_time': os.path.getmtime(path),
+                'size': os.path.getsize(path),
+                'info': info
+            }
+            self.changed = True
+
+    def save(self):
+        """
+        write cache, when new files are probed
+        """
+        if not _general.probe_cache:
+            return
+
+        with self.lock:
+            if not self.changed:
+                return
+
+            temp_file = _general.probe_cache_path + '.tmp'
+
+            try:
+                os.makedirs(os.path.dirname(temp_file), exist_ok=True)
+
+                with open(temp_file, 'w') as cache_file:
+                    json.dump(self.entries, cache_file)
+
+                os.replace(temp_file, _general.probe_cache_path)
+                self.changed = False
+            except OSError as err:
+                messenger.warning(f'Probe cache is not writable: {err}')
+
+
+probe_cache = ProbeCache()
+
+
+class Preroll:
+    """
+    probe the next clip some seconds before the current clip ends,
+    so the clip change has not to wait for ffprobe,
+    remote sources get connected earlier
+    """
+
+    def __init__(self):
+        self.lock = Lock()
+        self.infos = {}
+        self.timer = None
+
+    def schedule(self, source, delay):
+        """
+        cancel pending preroll and probe source after delay seconds
+        """
+        if self.timer:
+            self.timer.cancel()
+
+        with self.lock:
+            self.infos = {}
+
+        if not source:
+            return
+
+        self.timer = Timer(max(delay, 0), self.load, args=(source,))
+        self.timer.daemon = True
+        self.timer.start()
+
+    def load(self, source):
+        probe = MediaProbe()
+        probe.load(source)
+
+        if probe.format:
+            messenger.debug(f'Preroll next clip: "{probe.src}"')
+
+            with self.lock:
+                self.infos[probe.src] = {
+                    'format': probe.format,
+                    'streams': [s for s in probe.audio + probe.video if s]
+                }
+
+    def take(self, src):
+        with self.lock:
+            return self.infos.pop(src, None)
+
+
+preroll = Preroll()
+
+
+def is_live(node):
+    """
+    test if clip source is a live stream, by its url scheme,
+    or by the live field, file sources are never live
+    """
+    source = node.get('source')
+
+    if not isinstance(source, str) or '://' not in source:
+        return False
+
+    if node.get('live') is not None:
+        return bool(node['live'])
+
+    return source.split('://')[0].lower() in LIVE_SCHEMES
+
+
 class MediaProbe:
     """
     get infos about media file, similare to mediainfo
@@ -549,7 +1043,10 @@ class MediaProbe:
         self.audio = []
         self.video = []
 
-        if self.src and self.src.split('://')[0] in self.remote_source:
+        if self.src and self.src.split('://')[0].lower() in LIVE_SCHEMES:
+            # stream urls have ports and options, they are taken as they are
+            self.is_remote = True
+        elif self.src and self.src.split('://')[0] in self.remote_source:
             url = self.src.split('://')
             self.src = f'{url[0]}://{urllib.parse.quote(url[1])}'
             self.is_remote = True
@@ -562,17 +1059,27 @@ class MediaProbe:
 
                 return
 
-        cmd = ['ffprobe', '-v', 'quiet', '-print_format',
-               'json', '-show_format', '-show_streams', self.src]
+        use_cache = _general.probe_cache and not self.is_remote
+        info = preroll.take(self.src)
 
-        try:
-            info = json.loads(check_output(cmd).decode('UTF-8'))
-        except CalledProcessError as err:
-            messenger.error(f'MediaProbe error in: "{self.src}"\n{err}')
-            self.audio.append(None)
-            self.video.append(None)
+        if info is None and use_cache:
+            info = probe_cache.get(self.src)
 
-            return
+        if info is None:
+            cmd = ['ffprobe', '-v', 'quiet', '-print_format',
+                   'json', '-show_format', '-show_streams', self.src]
+
+            try:
+                info = json.loads(check_output(cmd).decode('UTF-8'))
+            except CalledProcessError as err:
+                messenger.error(f'MediaProbe error in: "{self.src}"\n{err}')
+                self.audio.append(None)
+                self.video.append(None)
+
+                return
+
+            if use_cache:
+                probe_cache.set(self.src, info)
 
         self.format = info['format']
 
@@ -602,16 +1109,17 @@ def handle_sigterm(sig, frame):
     """
     handler for ctrl+c signal
     """
+    is_terminated.set()
     raise(SystemExit)
 
 
 def handle_sighub(sig, frame):
     """
-    handling SIGHUB signal for reload configuration
-    Linux/macOS only
+    handling SIGHUB signal for reload configuration,
+    the playlist is read again at the next clip change
+    Linux/macOS only, the reload itself runs in the playout loop
     """
-    messenger.info('Reload config file')
-    load_config()
+    _global.reload = True
 
 
 signal.signal(signal.SIGTERM, handle_sigterm)
@@ -624,6 +1132,8 @@ def terminate_processes(watcher=None):
     """
     kill orphaned processes
     """
+    is_terminated.set()
+
     if _ff.decoder and _ff.decoder.poll() is None:
         _ff.decoder.terminate()
 
@@ -634,6 +1144,36 @@ def terminate_processes(watcher=None):
         watcher.stop()
 
 
+def run_hook(event, *args):
+    """
+    run external command from hooks config, with event values as arguments,
+    the command runs in background and can not stop the playout
+    """
+    cmd = getattr(_hooks, event, None)
+
+    if not cmd:
+        return
+
+    def wait():
+        try:
+            proc = Popen(shlex.split(cmd) + [str(arg) for arg in args],
+                         stdin=DEVNULL, stdout=DEVNULL, stderr=DEVNULL)
+        except (OSError, ValueError) as err:
+            messenger.error(f'Hook "{event}" could not start: {cmd}\n{err}')
+            return
+
+        code = proc.wait()
+
+        if code:
+            messenger.warning(f'Hook "{event}" ends with exit code {code}')
+
+    messenger.debug(f'Run hook "{event}": {cmd}')
+
+    thread = Thread(name=f'hook_{event}', target=wait)
+    thread.daemon = True
+    thread.start()
+
+
 def ffmpeg_stderr_reader(std_errors, decoder):
     """
     read fmpeg stderr decoder and encoder instance
@@ -690,11 +1230,11 @@ def get_delta(begin):
 
 def get_date(seek_day, next_start=0):
     """
-    get date for correct playlist,
+    get date for correct playlist, the playlist day begins at day_start,
     when seek_day is set:
     check if playlist date must be from yesterday
     """
-    d = date.today()
+    d = get_now().date()
 
     if seek_day and _playlist.start > get_time('full_sec'):
         return (d - timedelta(1)).strftime('%Y-%m-%d')
@@ -704,6 +1244,50 @@ def get_date(seek_day, next_start=0):
         return d.strftime('%Y-%m-%d')
 
 
+def playlist_file(path, list_date):
+    """
+    playlist file in path for date, from the filename template,
+    by default it is path/yyyy/mm/yyyy-mm-dd.json
+    """
+    name = datetime.strptime(list_date, '%Y-%m-%d').strftime(
+        _playlist.filename)
+
+    return os.path.join(path, *name.split('/'))
+
+
+def get_utc_offset():
+    """
+    current offset from channel time zone, None without time zone
+    """
+    if _general.timezone:
+        return datetime.now(_general.timezone).utcoffset()
+
+
+def dst_change(list_date):
+    """
+    find daylight saving time change in playlist day from list_date,
+    return the change in seconds and the first wall clock time
+    with the new offset, or None when there is no change
+    """
+    if not _general.timezone:
+        return None
+
+    # some time zones change at half hours, so check in 15 minute steps
+    start = _playlist.start or 0
+    day_start = datetime.strptime(list_date, '%Y-%m-%d') + timedelta(
+        seconds=start - start % 900)
+    offset = day_start.replace(tzinfo=_general.timezone).utcoffset()
+
+    for step in range(1, 97):
+        moment = day_start + timedelta(minutes=15 * step)
+        new_offset = moment.replace(tzinfo=_general.timezone).utcoffset()
+
+        if new_offset != offset:
+            return (new_offset - offset).total_seconds(), moment
+
+    return None
+
+
 def get_float(value, default=False):
     """
     test if value is float
@@ -714,11 +1298,95 @@ def get_float(value, default=False):
         return default
 
 
+def frame_rate(rate):
+    """
+    frame rate from ffprobe value like 30000/1001, 0 when it is unknown
+    """
+    try:
+        a, b = str(rate).split('/')
+        return float(a) / float(b)
+    except (ValueError, ZeroDivisionError):
+        return 0
+
+
+def parse_scale(value):
+    """
+    clip scale like 768x576, return width and height,
+    or None when it is not valid or bigger then the output
+    """
+    match = re.match(r'^\s*(\d+)\s*[x:]\s*(\d+)\s*$', str(value))
+
+    if not match:
+        return None
+
+    width, height = int(match.group(1)), int(match.group(2))
+
+    if not 0 < width <= _pre.w or not 0 < height <= _pre.h:
+        return None
+
+    return width, height
+
+
+def clip_speed(node):
+    """
+    playback speed from clip, 1.0 when it is not set or not valid
+    """
+    speed = get_float(node.get('speed'), 1.0)
+
+    return speed if speed > 0 else 1.0
+
+
+def vfr_info(probe):
+    """
+    real and average frame rate, when the video stream has a variable
+    frame rate, otherwise None
+    """
+    if not probe.video or not probe.video[0]:
+        return None
+
+    r_fps = frame_rate(probe.video[0].get('r_frame_rate'))
+    avg_fps = frame_rate(probe.video[0].get('avg_frame_rate'))
+
+    if r_fps and avg_fps and not math.isclose(r_fps, avg_fps, abs_tol=0.01):
+        return r_fps, avg_fps
+
+    return None
+
+
 def is_advertisement(node):
     if node and node.get('category') == 'advertisement':
         return True
 
 
+def clip_meta(node):
+    """
+    category and tags from clip, as log fields
+    """
+    meta = {}
+
+    if node.get('category'):
+        meta['category'] = node['category']
+
+    if node.get('tags'):
+        meta['tags'] = ', '.join(node['tags'])
+
+    return meta
+
+
+def json_error(err):
+    """
+    describe json error with line, column and text from the line
+    """
+    if not isinstance(err, json.JSONDecodeError):
+        return str(err)
+
+    lines = err.doc.splitlines()
+    line = lines[err.lineno - 1].strip() if err.lineno <= len(lines) else ''
+
+    return (f'{err.msg} in line {err.lineno}, column {err.colno}: '
+            f'{line[:120]}')
+
+
 def valid_json(file):
     """
     simple json validation
@@ -726,11 +1394,310 @@ def valid_json(file):
     try:
         json_object = json.load(file)
         return json_object
-    except ValueError:
-        messenger.error(f'Playlist {file.name} is not JSON conform')
+    except ValueError as err:
+        messenger.error(
+            f'Playlist {{current_file}} is not JSON conform\n'
+            f'{json_error(err)}', current_file=file.name)
         return None
 
 
+def read_m3u(lines):
+    """
+    convert m3u/m3u8 lines to json playlist nodes,
+    clips without duration get the dummy length
+    """
+    json_object = {'program': []}
+    duration = 0
+    title = None
+
+    for line in lines:
+        line = line.strip()
+
+        if line.startswith('#EXTINF:'):
+            match = re.match(r'#EXTINF:\s*(-?[\d.]+)[^,]*,?(.*)', line)
+            duration = get_float(match[1], 0) if match else 0
+            title = match[2].strip() if match else None
+        elif line and not line.startswith('#'):
+            if duration <= 0:
+                duration = _playlist.dummy_len or DUMMY_LEN
+
+            node = {
+                'in': 0,
+                'out': duration,
+                'duration': duration,
+                'source': line
+            }
+
+            if title:
+                node['title'] = title
+
+            json_object['program'].append(node)
+            duration = 0
+            title = None
+
+    return json_object
+
+
+def is_yaml(path, content_type=''):
+    """
+    test by extension, or by content type from remote playlist,
+    if playlist is in yaml format
+    """
+    if path.lower().endswith('.gz'):
+        path = path[:-3]
+
+    return path.lower().endswith(('.yml', '.yaml')) \
+        or 'yaml' in (content_type or '').lower()
+
+
+def playlist_format(name, content, content_type=''):
+    """
+    find playlist format by the begin of the content, then by extension
+    or content type, yaml keys, like "program:", win against json
+    extension, when nothing fits, the default format from config is taken
+    """
+    text = content.lstrip()
+    path = name.lower()[:-3] if name.lower().endswith('.gz') else name.lower()
+    content_type = (content_type or '').lower()
+
+    if text.startswith(('{', '[')):
+        playlist_type, reason = 'json', 'content'
+    elif text.startswith('#EXTM3U'):
+        playlist_type, reason = 'm3u', 'content'
+    elif is_yaml(name, content_type):
+        playlist_type, reason = 'yaml', 'extension or content type'
+    elif path.endswith(('.m3u', '.m3u8')) or 'mpegurl' in content_type:
+        playlist_type, reason = 'm3u', 'extension or content type'
+    elif re.match(r'(#.*\n\s*)*(---|[A-Za-z_][\w-]*\s*:(\s|$))', text):
+        playlist_type, reason = 'yaml', 'content'
+    elif path.endswith('.json') or 'json' in content_type:
+        playlist_type, reason = 'json', 'extension or content type'
+    else:
+        playlist_type, reason = _playlist.default_format, 'default format'
+
+    messenger.debug(f'Playlist {{current_file}} is read as {playlist_type}, '
+                    f'found by {reason}', current_file=name)
+
+    return playlist_type
+
+
+def read_yaml(content):
+    """
+    load yaml playlist, a date value is converted back to string,
+    like it is in json playlists
+    """
+    try:
+        json_object = yaml.safe_load(content)
+    except yaml.YAMLError as err:
+        raise ValueError(f'YAML error: {err}')
+
+    if isinstance(json_object, dict) \
+            and isinstance(json_object.get('date'), date):
+        json_object['date'] = json_object['date'].isoformat()
+
+    return json_object
+
+
+def is_gzip(path, data=b''):
+    """
+    test by extension, or by magic number, if playlist is gzip compressed
+    """
+    return path.lower().endswith('.gz') or data[:2] == b'\x1f\x8b'
+
+
+def gunzip(data):
+    """
+    decompress gzip playlist, the decompressed size is limited,
+    so a broken or bad file can not fill up the memory
+    """
+    try:
+        with gzip.GzipFile(fileobj=BytesIO(data)) as f:
+            content = f.read(MAX_GZIP_SIZE + 1)
+    except (OSError, EOFError) as err:
+        raise ValueError(f'Decompression failed: {err}')
+
+    if len(content) > MAX_GZIP_SIZE:
+        raise ValueError(
+            f'Decompressed playlist is bigger then {MAX_GZIP_SIZE} bytes')
+
+    return content.decode('utf-8')
+
+
+_stdin_playlist = SimpleNamespace(lock=Lock(), content=None, mod_time=0.0)
+
+
+def read_stdin():
+    """
+    playlist from stdin, for playlist path "-", stdin can be read only
+    once, so the content is kept with the time when it was read
+    """
+    with _stdin_playlist.lock:
+        if _stdin_playlist.content is None:
+            _stdin_playlist.content = sys.stdin.buffer.read()
+            _stdin_playlist.mod_time = get_time('stamp')
+
+        return _stdin_playlist.content, _stdin_playlist.mod_time
+
+
+def parse_playlist(name, content, content_type=''):
+    """
+    parse playlist content, in json, yaml or m3u format
+    """
+    # servers can send the BOM from the file
+    content = content.lstrip('\ufeff')
+    playlist_type = playlist_format(name, content, content_type)
+
+    if playlist_type == 'yaml':
+        return read_yaml(content)
+
+    if playlist_type == 'm3u':
+        return read_m3u(content.splitlines())
+
+    return json.loads(content)
+
+
+class JsonStream:
+    """
+    read json values one by one from a file, only the current chunk
+    and the current value are hold in memory, with limit the read
+    characters are limited, like for decompressed playlists
+    """
+
+    def __init__(self, file, chunk_size=65536, limit=None):
+        self.file = file
+        self.chunk_size = chunk_size
+        self.limit = limit
+        self.size = 0
+        self.decoder = json.JSONDecoder()
+        self.buffer = ''
+        self.eof = False
+
+    def fill(self):
+        chunk = self.file.read(self.chunk_size)
+        self.size += len(chunk)
+
+        if not chunk:
+            self.eof = True
+
+        if self.limit and self.size > self.limit:
+            raise ValueError(
+                f'Decompressed playlist is bigger then {self.limit} bytes')
+
+        self.buffer += chunk
+
+        return not self.eof
+
+    def peek(self):
+        """
+        skip whitespace and return next character
+        """
+        while True:
+            self.buffer = self.buffer.lstrip()
+
+            if self.buffer or not self.fill():
+                return self.buffer[:1]
+
+    def expect(self, char):
+        if self.peek() != char:
+            raise ValueError(f'Expecting "{char}", got: "{self.peek()}"')
+
+        self.buffer = self.buffer[1:]
+
+    def value(self):
+        self.peek()
+
+        while True:
+            try:
+                value, end = self.decoder.raw_decode(self.buffer)
+
+                # a number at the end of the buffer can be cut
+                if self.buffer[end:].strip() or self.eof:
+                    self.buffer = self.buffer[end:]
+                    return value
+            except json.JSONDecodeError as err:
+                if self.eof:
+                    # position in the file, the buffer is only a part
+                    position = self.size - len(self.buffer) + err.pos
+                    raise ValueError(
+                        f'{err.msg} at character {position}') from None
+
+            self.fill()
+
+    def items(self, rest=None):
+        """
+        iterate over the values of the current array, with rest the
+        values after the array are read in it, until the object ends
+        """
+        try:
+            while self.peek() != ']':
+                yield self.value()
+
+                if self.peek() == ',':
+                    self.expect(',')
+
+            self.expect(']')
+
+            if rest is not None:
+                if self.peek() == ',':
+                    self.expect(',')
+
+                self.members(rest)
+        finally:
+            self.file.close()
+
+    def members(self, values, stop=None):
+        """
+        read key and value pairs from the current object in values,
+        until the key stop comes, return True when it is found
+        """
+        while self.peek() != '}':
+            if not self.peek():
+                raise ValueError('Expecting "}", got end of file')
+
+            key = self.value()
+            self.expect(':')
+
+            if key == stop:
+                return True
+
+            values[key] = self.value()
+
+            if self.peek() == ',':
+                self.expect(',')
+
+        return False
+
+
+def read_json_streaming(json_file):
+    """
+    read big json playlist clip by clip, instead of loading it complete,
+    return the values before 'program', like date, and an iterator
+    over the clips, values after 'program' are added to the header,
+    when the iterator is at the end
+    """
+    if is_gzip(json_file):
+        stream = JsonStream(gzip.open(json_file, 'rt', encoding='utf-8-sig'),
+                            limit=MAX_GZIP_SIZE)
+    else:
+        stream = JsonStream(open(json_file, 'r', encoding='utf-8-sig'))
+
+    header = {}
+
+    try:
+        stream.expect('{')
+
+        if stream.members(header, 'program'):
+            stream.expect('[')
+            return header, stream.items(header)
+    except (ValueError, OSError, EOFError):
+        stream.file.close()
+        raise
+
+    stream.file.close()
+
+    return header, iter([])
+
+
 def check_sync(delta):
     """
     check that we are in tolerance time
@@ -759,29 +1726,40 @@ def seek_in(seek):
         return []
 
 
-def set_length(duration, seek, out):
+def set_length(duration, seek, out, speed=1.0):
     """
-    set new clip length
+    set new clip length, with speed the output is shorter
     """
     if out < duration:
-        return ['-t', str(out - seek)]
+        return ['-t', str((out - seek) / speed)]
     else:
         return []
 
 
-def loop_input(source, src_duration, target_duration):
+def loop_input(source, src_duration, target_duration, speed=1.0):
     # loop filles n times
     loop_count = math.ceil(target_duration / src_duration)
     messenger.info(f'Loop "{source}" {loop_count} times, '
                    f'total duration: {target_duration:.2f}')
     return ['-stream_loop', str(loop_count),
-            '-i', source, '-t', str(target_duration)]
+            '-i', source, '-t', str(target_duration / speed)]
 
 
 def gen_dummy(duration):
     """
-    generate a dummy clip, with black color and empty audiotrack
+    generate a dummy clip, with black color and empty audiotrack,
+    or with test pattern and 1kHz tone, so it is clear on a monitor,
+    that the playout runs but has nothing to play
     """
+    if _playlist.dummy == 'testsrc':
+        return [
+            '-f', 'lavfi', '-i',
+            f'{_playlist.dummy_pattern}=s={_pre.w}x{_pre.h}:d={duration}:'
+            f'r={_pre.fps},format=pix_fmts=yuv420p',
+            '-f', 'lavfi', '-i',
+            f'sine=f=1000:r=48000:d={duration}'
+        ]
+
     color = '#121212'
     # IDEA: add noise could be an config option
     # noise = 'noise=alls=50:allf=t+u,hue=s=0'
@@ -799,7 +1777,11 @@ def gen_filler(node):
     """
     probe = MediaProbe()
     probe.load(_storage.filler)
-    duration = node['out'] - node['seek']
+    duration = (node['out'] - node['seek']) / clip_speed(node)
+
+    if node.pop('speed', None) is not None:
+        # filler plays in normal speed, for the same time
+        node['out'] = node['seek'] + duration
 
     node['probe'] = probe
 
@@ -835,24 +1817,94 @@ def gen_filler(node):
         return node
 
 
-def src_or_dummy(node):
+def playable(probe):
+    """
+    test if probed source can be played
+    """
+    if probe.is_remote:
+        return bool(probe.video and probe.video[0])
+
+    return probe.format is not None
+
+
+def alternate_source(node, probe):
+    """
+    when the clip source is not playable, take the first playable
+    one from the alternate sources, return source and its probe
+    """
+    source = node.get('source')
+
+    if playable(probe) or not isinstance(node.get('sources'), list):
+        return source, probe
+
+    for alternate in node['sources']:
+        alternate_probe = MediaProbe()
+        alternate_probe.load(alternate)
+
+        if playable(alternate_probe):
+            return alternate, alternate_probe
+
+    return source, probe
+
+
+def background_input(node):
+    """
+    video input for an audio only clip, the audio_background loops,
+    without it a black color is taken, return input and its probe,
+    the probe is None for the color
+    """
+    background = _pre.audio_background
+
+    if background and os.path.isfile(background):
+        probe = MediaProbe()
+        probe.load(background)
+
+        if probe.video and probe.video[0]:
+            return ['-stream_loop', '-1', '-i', background], probe
+
+    messenger.warning('No audio_background for audio only clip, show '
+                      f'black color: "{node.get("source")}"')
+
+    return ['-f', 'lavfi', '-i', f'color=c=black:s={_pre.w}x{_pre.h}:'
+            f'r={_pre.fps},format=pix_fmts=yuv420p'], None
+
+
+def src_or_dummy(node, probe=None):
     """
     when source path exist, generate input with seek and out time
-    when path not exist, generate dummy clip
+    when path not exist, generate dummy clip,
+    a probe from the source can be given, then it is not probed again
     """
 
-    probe = MediaProbe()
-    probe.load(node.get('source'))
+    if probe is None:
+        probe = MediaProbe()
+        probe.load(node.get('source'))
+
+    source, probe = alternate_source(node, probe)
+    node.pop('background_probe', None)
+
+    if source != node.get('source'):
+        messenger.warning(f'Source "{node.get("source")}" is not playable, '
+                          f'play alternate source: "{source}"')
+        node['source'] = source
+
     node['probe'] = probe
+    speed = clip_speed(node)
 
     # check if input is a remote source
-    if probe.is_remote and probe.video[0]:
-        if node['seek'] > 0.0:
+    if node.get('checksum_failed'):
+        messenger.error(
+            f'Checksum from "{node.get("source")}" is wrong, play filler')
+        return gen_filler(node)
+    elif probe.is_remote and (probe.video and probe.video[0]
+                              or node.get('mode') == 'audio'
+                              and probe.audio):
+        if node['seek'] > 0.0 and not is_live(node):
             messenger.warning(
                 f'Seek in remote source "{node.get("source")}" not supported!')
         node['src_cmd'] = [
             '-i', node['source']
-            ] + set_length(86400, node['seek'], node['out'])
+            ] + set_length(86400, node['seek'], node['out'], speed)
     elif node.get('source') and os.path.isfile(node['source']):
         if node['out'] > node['duration']:
             if node['seek'] > 0.0:
@@ -861,18 +1913,30 @@ def src_or_dummy(node):
                 node['src_cmd'] = [
                     '-i', node['source']
                     ] + set_length(node['duration'], node['seek'],
-                                   node['out'] - node['seek'])
+                                   node['out'] - node['seek'], speed)
             else:
                 # FIXME: when list starts with looped clip,
                 # the logo length will be wrong
                 node['src_cmd'] = loop_input(node['source'], node['duration'],
-                                             node['out'])
+                                             node['out'], speed)
         else:
             node['src_cmd'] = seek_in(node['seek']) + \
-                ['-i', node['source']] + set_length(node['duration'],
-                                                    node['seek'], node['out'])
+                ['-i', node['source']] + set_length(
+                    node['duration'], node['seek'], node['out'], speed)
     else:
-        node = gen_filler(node)
+        return gen_filler(node)
+
+    if node.get('mode') == 'audio':
+        # background comes as second input, after the clip, so the length
+        # arguments from the clip belong to the background, the clip audio
+        # gets trimmed in the filtergraph
+        background, node['background_probe'] = background_input(node)
+        node['src_cmd'] += background
+
+    if node.get('input_args') and _playlist.allow_input_args:
+        # clip args are for the input, they must come before -i
+        index = node['src_cmd'].index('-i')
+        node['src_cmd'][index:index] = node['input_args']
 
     return node
 
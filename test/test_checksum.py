@@ -0,0 +1,128 @@
+#!/usr/bin/env python3
+# -*- coding: utf-8 -*-
+
+"""
+Test checksums from local and remote clips, with a fake http session.
+Run with: python3 test/test_checksum.py
+"""
+
+import hashlib
+import io
+import os
+import sys
+import tempfile
+import unittest
+from types import SimpleNamespace
+
+import requests
+
+ROOT = os.path.dirname(os.path.dirname(os.path.abspath(__file__)))
+sys.path.append(ROOT)
+
+# ffplayout reads the arguments at import
+ARGV = sys.argv
+sys.argv = [sys.argv[0], '-l', 'none',
+            '-c', os.path.join(ROOT, 'ffplayout.yml')]
+
+from ffplayout import playlist  # noqa: E402
+from ffplayout.playlist import ValidationReport, check_checksum  # noqa: E402
+
+URL = 'http://media.example/clip.mp4'
+CONTENT = b'clip content ' * 1000
+SHA256 = hashlib.sha256(CONTENT).hexdigest()
+MD5 = hashlib.md5(CONTENT).hexdigest()
+
+
+class FakeSession:
+    """
+    answer every request with the same status and body
+    """
+
+    def __init__(self, status=200, body=CONTENT):
+        self.status = status
+        self.body = body
+        self.requests = 0
+
+    def __call__(self, url):
+        return self
+
+    def __enter__(self):
+        return self
+
+    def __exit__(self, *args):
+        return False
+
+    def get(self, url, **kwargs):
+        self.requests += 1
+        result = requests.Response()
+        result.status_code = self.status
+        result.url = url
+        result.raw = io.BytesIO(self.body)
+
+        return result
+
+
+class TestChecksum(unittest.TestCase):
+
+    def setUp(self):
+        self.report = ValidationReport('2021-02-14')
+        self.remote = SimpleNamespace(is_remote=True)
+
+    def check(self, node, session):
+        return check_checksum(node, 1, self.remote, self.report, session)
+
+    def test_remote_match(self):
+        session = FakeSession()
+
+        self.assertIsNone(self.check({'source': URL, 'sha256': SHA256},
+                                     session))
+        self.assertIsNone(self.check({'source': URL, 'md5': MD5.upper()},
+                                     session))
+        self.assertEqual(session.requests, 2)
+
+    def test_remote_mismatch(self):
+        # a cut download has an other checksum
+        session = FakeSession(body=CONTENT[:-100])
+
+        self.assertEqual(self.check({'source': URL, 'sha256': SHA256},
+                                    session), 'sha256')
+
+    def test_remote_not_reachable(self):
+        # the clip is not marked as broken, only a warning is logged
+        session = FakeSession(status=404)
+
+        self.assertIsNone(self.check({'source': URL, 'sha256': '00'},
+                                     session))
+
+    def test_remote_too_big(self):
+        size = playlist.REMOTE_CHECKSUM_BYTES
+        playlist.REMOTE_CHECKSUM_BYTES = 1000
+
+        try:
+            self.assertIsNone(self.check({'source': URL, 'sha256': '00'},
+                                         FakeSession()))
+        finally:
+            playlist.REMOTE_CHECKSUM_BYTES = size
+
+    def test_stream_is_not_downloaded(self):
+        session = FakeSession()
+        node = {'source': 'rtmp://media.example/live', 'sha256': '00'}
+
+        self.assertIsNone(self.check(node, session))
+        self.assertEqual(session.requests, 0)
+
+    def test_local_file(self):
+        with tempfile.NamedTemporaryFile(suffix='.mp4') as f:
+            f.write(CONTENT)
+            f.flush()
+            local = SimpleNamespace(is_remote=False)
+
+            self.assertIsNone(check_checksum(
+                {'source': f.name, 'sha256': SHA256}, 1, local, self.report))
+            self.assertEqual(check_checksum(
+                {'source': f.name, 'md5': '00'}, 1, local, self.report),
+                'md5')
+
+
+if __name__ == '__main__':
+    unittest.main(argv=ARGV)
@@ -0,0 +1,123 @@
+#!/usr/bin/env python3
+# -*- coding: utf-8 -*-
+
+"""
+Test reading big json playlists clip by clip.
+Run with: python3 test/test_json_stream.py
+"""
+
+import gzip
+import io
+import json
+import os
+import sys
+import tempfile
+import unittest
+
+ROOT = os.path.dirname(os.path.dirname(os.path.abspath(__file__)))
+sys.path.append(ROOT)
+
+# ffplayout reads the arguments at import
+ARGV = sys.argv
+sys.argv = [sys.argv[0], '-l', 'none',
+            '-c', os.path.join(ROOT, 'ffplayout.yml')]
+
+from ffplayout import playlist  # noqa: E402
+from ffplayout.utils import JsonStream, read_json_streaming  # noqa: E402
+
+PLAYLIST = {
+    'channel': 'Test 1',
+    'date': '2021-02-14',
+    'program': [{
+        'in': 0,
+        'out': 60.5,
+        'duration': 60.5,
+        'source': f'/Media/clip{i}.mp4',
+        'category': 'ad' if i % 3 else ''
+    } for i in range(50)],
+    'comment': 'keys after program are kept',
+    'version': 2
+}
+
+
+class TestJsonStream(unittest.TestCase):
+
+    def setUp(self):
+        self.folder = tempfile.TemporaryDirectory()
+
+    def tearDown(self):
+        self.folder.cleanup()
+
+    def write(self, name, content):
+        path = os.path.join(self.folder.name, name)
+
+        if name.endswith('.gz'):
+            with gzip.open(path, 'wt', encoding='utf-8') as f:
+                f.write(content)
+        else:
+            with open(path, 'w', encoding='utf-8') as f:
+                f.write(content)
+
+        return path
+
+    def test_values_over_chunks(self):
+        # small chunks cut values and numbers in the middle
+        stream = JsonStream(io.StringIO('[1, 22.5, {"a": [3, 4]}, "x y"]'),
+                            chunk_size=3)
+        stream.expect('[')
+
+        self.assertEqual(list(stream.items()), [1, 22.5, {'a': [3, 4]},
+                                                'x y'])
+
+    def test_header_and_keys_after_program(self):
+        path = self.write('2021-02-14.json', json.dumps(PLAYLIST, indent=4))
+        header, clips = read_json_streaming(path)
+
+        self.assertEqual(header, {'channel': 'Test 1', 'date': '2021-02-14'})
+        self.assertEqual(list(clips), PLAYLIST['program'])
+        self.assertEqual(header['comment'], PLAYLIST['comment'])
+        self.assertEqual(header['version'], 2)
+
+    def test_gzip(self):
+        path = self.write('2021-02-14.json.gz', json.dumps(PLAYLIST))
+        header, clips = read_json_streaming(path)
+
+        self.assertEqual(list(clips), PLAYLIST['program'])
+        self.assertEqual(header['date'], '2021-02-14')
+        self.assertEqual(header['version'], 2)
+
+    def test_without_program(self):
+        path = self.write('2021-02-14.json', '{"date": "2021-02-14"}')
+        header, clips = read_json_streaming(path)
+
+        self.assertEqual(header, {'date': '2021-02-14'})
+        self.assertEqual(list(clips), [])
+
+    def test_broken_file(self):
+        text = json.dumps(PLAYLIST)
+        path = self.write('2021-02-14.json', text[:len(text) // 2])
+        _, clips = read_json_streaming(path)
+
+        with self.assertRaises(ValueError):
+            list(clips)
+
+    def test_reader_streams_big_files(self):
+        size = playlist.STREAM_BYTES
+        playlist.STREAM_BYTES = 0
+        reader = playlist.PlaylistReader('2021-02-14', 0.0, validate=False)
+
+        try:
+            for name in ['2021-02-14.json', '2021-02-14.json.gz']:
+                path = self.write(name, json.dumps(PLAYLIST))
+
+                self.assertEqual(reader.load_file(path), PLAYLIST)
+
+            path = self.write('broken.json', '{"program": [{"in": 0}')
+
+            self.assertIsNone(reader.load_file(path))
+        finally:
+            playlist.STREAM_BYTES = size
+
+
+if __name__ == '__main__':
+    unittest.main(argv=ARGV)
@@ -0,0 +1,250 @@
+#!/usr/bin/env python3
+# -*- coding: utf-8 -*-
+
+"""
+Test remote playlists with a fixed clock and a fake http session,
+so no network and no wall clock is needed.
+Run with: python3 test/test_remote_playlist.py
+"""
+
+import io
+import json
+import os
+import sys
+import threading
+import time
+import unittest
+
+import requests
+from urllib3.exceptions import ProtocolError
+
+ROOT = os.path.dirname(os.path.dirname(os.path.abspath(__file__)))
+sys.path.append(ROOT)
+
+# ffplayout reads the arguments at import
+ARGV = sys.argv
+sys.argv = [sys.argv[0], '-l', 'none',
+            '-c', os.path.join(ROOT, 'ffplayout.yml')]
+
+from ffplayout.playlist import PlaylistReader  # noqa: E402
+from ffplayout.utils import _playlist, is_terminated  # noqa: E402
+
+URL = 'http://playlists.example/2021/02/2021-02-14.json'
+NOW = 1613282400.0
+PLAYLIST = {
+    'channel': 'Test 1',
+    'date': '2021-02-14',
+    'program': [{
+        'in': 0,
+        'out': 60,
+        'duration': 60,
+        'source': '/Media/clip.mp4'
+    }]
+}
+
+
+def response(status, body=b'', headers=None):
+    result = requests.Response()
+    result.status_code = status
+    result.url = URL
+    result.raw = io.BytesIO(body)
+    result.headers.update(headers or {})
+
+    return result
+
+
+class CutBody:
+    """
+    body which is cut off in the transfer
+    """
+
+    def stream(self, *args, **kwargs):
+        yield b'{"program": ['
+        raise ProtocolError('Connection broken: IncompleteRead')
+
+    def close(self):
+        pass
+
+
+class FakeSession:
+    """
+    give the responses one after the other, and keep the request headers
+    """
+
+    def __init__(self, *responses):
+        self.responses = list(responses)
+        self.requests = []
+
+    def __call__(self, url):
+        return self
+
+    def __enter__(self):
+        return self
+
+    def __exit__(self, *args):
+        return False
+
+    def get(self, url, headers=None, **kwargs):
+        self.requests.append(dict(headers or {}))
+
+        return self.responses.pop(0)
+
+
+class SlowSession(FakeSession):
+    """
+    every request fails after a while, like an overloaded server
+    """
+
+    def get(self, url, headers=None, **kwargs):
+        self.requests.append(dict(headers or {}))
+        time.sleep(0.05)
+
+        return response(503)
+
+
+def json_response(status=200, headers=None):
+    headers = dict({'Content-Type': 'application/json'}, **(headers or {}))
+
+    return response(status, json.dumps(PLAYLIST).encode(), headers)
+
+
+class TestRemotePlaylist(unittest.TestCase):
+
+    def setUp(self):
+        self.config = vars(_playlist).copy()
+        _playlist.remote_attempts = 3
+        _playlist.remote_backoff = 0
+        _playlist.remote_cache = None
+        _playlist.remote_headers = {}
+        _playlist.remote_content_types = None
+
+    def tearDown(self):
+        vars(_playlist).clear()
+        vars(_playlist).update(self.config)
+        is_terminated.clear()
+
+    def reader(self, session):
+        return PlaylistReader('2021-02-14', 0.0, validate=False,
+                              clock=lambda: NOW, session=session)
+
+    def test_success(self):
+        session = FakeSession(json_response())
+        reader = self.reader(session)
+        reader.read_source(URL)
+
+        self.assertFalse(reader.error)
+        self.assertEqual(reader.source, URL)
+        self.assertEqual(len(reader.nodes['program']), 1)
+        # without last-modified header the clock is taken
+        self.assertEqual(reader.last_mod_time, NOW)
+
+    def test_last_modified(self):
+        session = FakeSession(json_response(headers={
+            'Last-Modified': 'Sun, 14 Feb 2021 05:00:00 GMT'}))
+        reader = self.reader(session)
+        reader.read_source(URL)
+
+        self.assertFalse(reader.error)
+        self.assertEqual(reader.last_mod_time, 1613278800.0)
+
+    def test_malformed_last_modified(self):
+        session = FakeSession(json_response(headers={
+            'Last-Modified': 'yesterday'}))
+        reader = self.reader(session)
+        reader.read_source(URL)
+
+        self.assertFalse(reader.error)
+        self.assertEqual(reader.last_mod_time, NOW)
+
+    def test_failure(self):
+        session = FakeSession(*[response(500) for _ in range(3)])
+        reader = self.reader(session)
+        reader.read_source(URL)
+
+        self.assertTrue(reader.error)
+        self.assertIsNone(reader.nodes)
+        # server errors are tried again, until the attempts are used
+        self.assertEqual(len(session.requests), 3)
+
+    def test_cut_body_is_tried_again(self):
+        cut = response(200, headers={'Content-Type': 'application/json'})
+        cut.raw = CutBody()
+        session = FakeSession(cut, json_response())
+        reader = self.reader(session)
+        reader.read_source(URL)
+
+        self.assertFalse(reader.error)
+        self.assertEqual(len(session.requests), 2)
+        self.assertEqual(len(reader.nodes['program']), 1)
+
+    def test_not_found(self):
+        session = FakeSession(response(404))
+        reader = self.reader(session)
+        reader.read_source(URL)
+
+        self.assertTrue(reader.error)
+        self.assertEqual(len(session.requests), 1)
+
+    def test_not_modified(self):
+        session = FakeSession(json_response(headers={'ETag': '"v1"'}),
+                              response(304))
+        reader = self.reader(session)
+        reader.read_source(URL)
+        reader.read_source(URL)
+
+        self.assertFalse(reader.error)
+        self.assertEqual(session.requests[1].get('If-None-Match'), '"v1"')
+        self.assertEqual(reader.last_mod_time, NOW)
+        self.assertEqual(len(reader.nodes['program']), 1)
+
+    def test_not_modified_after_drop(self):
+        # the playlist was dropped in between, the cached one is taken
+        session = FakeSession(json_response(headers={'ETag': '"v1"'}),
+                              response(304))
+        reader = self.reader(session)
+        reader.read_source(URL)
+        reader.nodes = None
+        reader.last_mod_time = 0.0
+        reader.read_source(URL)
+
+        self.assertFalse(reader.error)
+        self.assertEqual(reader.last_mod_time, NOW)
+        self.assertEqual(reader.nodes['program'][0]['source'],
+                         '/Media/clip.mp4')
+
+    def test_malformed_body(self):
+        session = FakeSession(response(200, b'{"program": [',
+                                       {'Content-Type': 'application/json'}))
+        reader = self.reader(session)
+        reader.read_source(URL)
+
+        self.assertTrue(reader.error)
+        self.assertIsNone(reader.nodes)
+
+    def test_terminate_stops_retries(self):
+        _playlist.remote_attempts = 100
+        _playlist.remote_backoff = 0.1
+        session = SlowSession()
+        reader = self.reader(session)
+        thread = threading.Thread(target=reader.read_source, args=(URL,))
+        thread.start()
+        time.sleep(0.3)
+        is_terminated.set()
+        thread.join(2)
+
+        self.assertFalse(thread.is_alive())
+        self.assertTrue(reader.error)
+        self.assertLess(len(session.requests), 10)
+
+    def test_terminated_before_read(self):
+        is_terminated.set()
+        session = FakeSession(json_response())
+        reader = self.reader(session)
+        reader.read_source(URL)
+
+        self.assertTrue(reader.error)
+        self.assertEqual(session.requests, [])
+
+
+if __name__ == '__main__':
+    unittest.main(argv=ARGV)
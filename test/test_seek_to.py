@@ -0,0 +1,117 @@
+#!/usr/bin/env python3
+# -*- coding: utf-8 -*-
+
+"""
+Test which clip seek_to finds for a time, like the playout at start.
+Run with: python3 test/test_seek_to.py
+"""
+
+import os
+import sys
+import unittest
+
+ROOT = os.path.dirname(os.path.dirname(os.path.abspath(__file__)))
+sys.path.append(ROOT)
+
+# ffplayout reads the arguments at import
+ARGV = sys.argv
+sys.argv = [sys.argv[0], '-l', 'none',
+            '-c', os.path.join(ROOT, 'ffplayout.yml')]
+
+from ffplayout.playlist import (ValidationReport, check_begin,  # noqa: E402
+                                seek_to)
+from ffplayout.utils import _playlist  # noqa: E402
+
+
+def clip(out, **values):
+    return dict({'in': 0, 'out': out, 'duration': out,
+                 'source': '/Media/clip.mp4'}, **values)
+
+
+class TestSeekTo(unittest.TestCase):
+
+    def setUp(self):
+        self.start = _playlist.start
+        # day starts at 06:00:00
+        _playlist.start = 21600.0
+
+    def tearDown(self):
+        _playlist.start = self.start
+
+    def test_before_first_clip(self):
+        program = [clip(60), clip(60)]
+
+        self.assertEqual(seek_to(program, 21000.0), (0, 0.0))
+
+    def test_inside_clip(self):
+        program = [clip(60), clip(60)]
+
+        self.assertEqual(seek_to(program, 21690.0), (1, 30.0))
+
+    def test_on_clip_boundary(self):
+        program = [clip(60), clip(60)]
+
+        # at the end of a clip the next one is taken
+        self.assertEqual(seek_to(program, 21660.0), (1, 0.0))
+        self.assertEqual(seek_to(program, 21600.0), (0, 0.0))
+
+    def test_after_last_clip(self):
+        program = [clip(60), clip(60)]
+
+        self.assertIsNone(seek_to(program, 21720.0))
+        self.assertIsNone(seek_to(program, 30000.0))
+
+    def test_inside_gap(self):
+        # clips end before the begin, the gap is not filled,
+        # so the clip with begin starts after them
+        program = [clip(60), clip(60, begin_at=21900.0)]
+
+        self.assertEqual(seek_to(program, 21690.0), (1, 30.0))
+        self.assertIsNone(seek_to(program, 21800.0))
+
+    def test_zero_length_clip(self):
+        # a clip which is cut away is never found
+        program = [clip(60), clip(0), clip(60)]
+
+        self.assertEqual(seek_to(program, 21660.0), (2, 0.0))
+
+    def test_begin_cuts_clip_before(self):
+        program = [clip(60), clip(60, begin_at=21630.0), clip(60)]
+
+        self.assertEqual(seek_to(program, 21620.0), (0, 20.0))
+        self.assertEqual(seek_to(program, 21640.0), (1, 10.0))
+        self.assertEqual(seek_to(program, 21700.0), (2, 10.0))
+
+    def test_hard_start_pads_clip_before(self):
+        program = [clip(60), clip(60, hard_start='06:02:00')]
+
+        self.assertEqual(seek_to(program, 21700.0), (0, 100.0))
+        self.assertEqual(seek_to(program, 21720.0), (1, 0.0))
+
+    def test_hard_start_cuts_clip_before(self):
+        program = [clip(60), clip(60, hard_start='06:00:30')]
+
+        self.assertEqual(seek_to(program, 21640.0), (1, 10.0))
+
+    def test_speed_before_hard_start(self):
+        # 120 seconds with double speed play 60 seconds
+        program = [clip(120, speed=2.0), clip(60, hard_start='06:01:00')]
+        report = ValidationReport()
+        check_begin(program, report)
+
+        self.assertEqual(seek_to(program, 21630.0), (0, 30.0))
+        self.assertEqual(seek_to(program, 21660.0), (1, 0.0))
+        self.assertEqual(report.issues, [])
+
+    def test_speed_cut_by_hard_start(self):
+        program = [clip(120, speed=2.0), clip(60, hard_start='06:00:50')]
+        report = ValidationReport()
+        check_begin(program, report)
+
+        self.assertEqual(seek_to(program, 21650.0), (1, 0.0))
+        self.assertEqual(len(report.issues), 1)
+        self.assertIn('cut by 10.00 seconds', str(report.issues[0]))
+
+
+if __name__ == '__main__':
+    unittest.main(argv=ARGV)